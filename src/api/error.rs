@@ -1,7 +1,77 @@
+use warp::hyper;
+
 use crate::data;
 
 #[derive(thiserror::Error, Debug)]
 pub enum ApiError {
     #[error("{0}")]
     DatabaseError(#[from] data::DataError),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{message}")]
+    Validation {
+        message: String,
+        details: Vec<String>,
+    },
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl warp::reject::Reject for ApiError {}
+
+impl ApiError {
+    pub fn validation(message: impl Into<String>) -> Self {
+        ApiError::Validation {
+            message: message.into(),
+            details: Vec::new(),
+        }
+    }
+
+    /// Values such as integers above `i64::MAX` are valid JSON, but cannot be saved.
+    pub fn unsavable(error: mongodb::bson::ser::Error) -> Self {
+        ApiError::Validation {
+            message: String::from("Some values cannot be saved"),
+            details: vec![error.to_string()],
+        }
+    }
+
+    pub fn status(&self) -> hyper::StatusCode {
+        match self {
+            ApiError::DatabaseError(data::DataError::NotFound) | ApiError::NotFound(_) => {
+                hyper::StatusCode::NOT_FOUND
+            }
+            ApiError::Validation { .. } => hyper::StatusCode::BAD_REQUEST,
+            ApiError::Conflict(_) => hyper::StatusCode::CONFLICT,
+            ApiError::DatabaseError(_) | ApiError::Internal(_) => {
+                hyper::StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    /// Lets clients tell errors apart without parsing their message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::DatabaseError(data::DataError::NotFound) | ApiError::NotFound(_) => {
+                "NOT_FOUND"
+            }
+            ApiError::Validation { .. } => "VALIDATION_FAILED",
+            ApiError::Conflict(_) => "CONFLICT",
+            ApiError::DatabaseError(_) | ApiError::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+
+    pub fn details(&self) -> &[String] {
+        match self {
+            ApiError::Validation { details, .. } => details,
+            _ => &[],
+        }
+    }
+}
+
+impl From<data::DataError> for warp::Rejection {
+    fn from(other: data::DataError) -> Self {
+        warp::reject::custom(ApiError::from(other))
+    }
 }
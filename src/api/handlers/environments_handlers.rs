@@ -1,19 +1,20 @@
 use std::collections::HashMap;
-use std::sync::Arc;
 
-use bollard::Docker;
+use chrono::Local;
 use mongodb::bson::doc;
 use mongodb::bson::oid::ObjectId;
 use serde::Deserialize;
-use tokio::sync::Mutex;
+use tracing::warn;
 use warp::hyper;
 
+use crate::api::handlers::{executions_handler, simulators_handlers};
 use crate::data::{
-    EnvironmentDTO, Execution, ExecutionDTO, Image, Scenario, Simulator, SimulatorDTO,
+    EnvironmentDTO, Execution, ExecutionDTO, ImageVersion, Scenario, Simulator, SimulatorDTO,
+    Suite, SuiteExecution, SuiteExecutionDTO,
 };
-use crate::domain::DockerScenarioExecutor;
+use crate::domain::ExecutionQueue;
 use crate::{
-    api::error_rejection::ErrorRejection,
+    api::error::ApiError,
     data::{Environment, Repository},
 };
 
@@ -37,10 +38,7 @@ pub async fn create(
         .await?;
 
     if !already_existing_environment.is_empty() {
-        return Err(ErrorRejection::reject(
-            "Environment already exists",
-            hyper::StatusCode::CONFLICT,
-        ));
+        return Err(ApiError::Conflict(String::from("Environment already exists")).into());
     }
 
     let environment = repository.create(environment).await?;
@@ -60,10 +58,7 @@ pub async fn find_by_id(
         .await?
     {
         Some(environment) => Ok(warp::reply::json(&EnvironmentDTO::from(environment))),
-        None => Err(ErrorRejection::reject(
-            "Could not find environment",
-            hyper::StatusCode::NOT_FOUND,
-        )),
+        None => Err(ApiError::NotFound(String::from("Could not find environment")).into()),
     }
 }
 
@@ -94,48 +89,82 @@ pub async fn find_simulator_by_id(
 
     match simulator {
         Some(simulator) => Ok(warp::reply::json(&SimulatorDTO::from(simulator))),
-        None => Err(ErrorRejection::reject(
-            "Could not find simulator",
-            hyper::StatusCode::NOT_FOUND,
-        )),
+        None => Err(ApiError::NotFound(String::from("Could not find simulator")).into()),
     }
 }
 
 pub async fn run_scenario_in_environment(
     repository: Repository,
-    environment_id: String,
-    scenario_id: String,
-    docker: Arc<Docker>,
+    environment_id: ObjectId,
+    scenario_id: ObjectId,
     web_socket: warp::ws::Ws,
-    mutex: Arc<Mutex<()>>,
+    execution_queue: ExecutionQueue,
+) -> Result<impl warp::reply::Reply, warp::Rejection> {
+    let execution =
+        queue_execution(&repository, &execution_queue, environment_id, scenario_id).await?;
+    let execution_id = execution
+        .id()
+        .ok_or_else(|| ApiError::Internal(String::from("Execution was not saved")))?
+        .to_owned();
+
+    // Subscribing before the upgrade keeps the events of executions that end in the meantime
+    let subscription = execution_queue.registry().subscribe(&execution_id);
+
+    Ok(web_socket.on_upgrade(move |web_socket| async move {
+        match subscription {
+            Some((rx, cancellation)) => {
+                executions_handler::forward_events(web_socket, rx, cancellation).await
+            }
+            None => executions_handler::replay_events(web_socket, &repository, &execution_id).await,
+        }
+    }))
+}
+
+pub async fn queue_scenario_in_environment(
+    repository: Repository,
+    environment_id: ObjectId,
+    scenario_id: ObjectId,
+    execution_queue: ExecutionQueue,
 ) -> Result<impl warp::reply::Reply, warp::Rejection> {
-    let scenario_id = ObjectId::parse_str(&scenario_id).expect("Invalid scenario id");
-    let environment_id = ObjectId::parse_str(&environment_id).expect("Invalid environment id");
+    let execution =
+        queue_execution(&repository, &execution_queue, environment_id, scenario_id).await?;
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&ExecutionDTO::from(execution)),
+        hyper::StatusCode::ACCEPTED,
+    ))
+}
 
+/// Saves a new execution of the scenario and queues it, so that it runs whether or not a client
+/// follows it.
+async fn queue_execution(
+    repository: &Repository,
+    execution_queue: &ExecutionQueue,
+    environment_id: ObjectId,
+    scenario_id: ObjectId,
+) -> Result<Execution, warp::Rejection> {
     let environment = repository
         .find_by_id::<Environment>(&environment_id)
         .await?
-        .expect("Environment not found");
+        .ok_or_else(|| ApiError::NotFound(String::from("Environment not found")))?;
 
     let scenario = repository
         .find_by_id::<Scenario>(&scenario_id)
         .await?
-        .expect("Scenario not found");
-
-    Ok(web_socket.on_upgrade(|web_socket| async move {
-        // Prevents concurrent executions
-        let _guard = mutex.lock().await;
-
-        DockerScenarioExecutor::run_scenario_in_environment(
-            docker,
-            &environment,
-            &scenario,
-            repository,
-            web_socket,
-        )
-        .await
-        .ok();
-    }))
+        .ok_or_else(|| ApiError::NotFound(String::from("Scenario not found")))?;
+
+    let execution = repository
+        .create(Execution::new(
+            scenario_id,
+            environment_id,
+            scenario.steps().clone(),
+            Local::now(),
+        ))
+        .await?;
+
+    execution_queue.queue(environment, scenario, execution.clone());
+
+    Ok(execution)
 }
 
 pub async fn executions_for_scenario_in_environment(
@@ -155,11 +184,61 @@ pub async fn executions_for_scenario_in_environment(
     ))
 }
 
+pub async fn run_suite_in_environment(
+    repository: Repository,
+    environment_id: ObjectId,
+    suite_id: ObjectId,
+    execution_queue: ExecutionQueue,
+) -> Result<impl warp::reply::Reply, warp::Rejection> {
+    let environment = repository
+        .find_by_id::<Environment>(&environment_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(String::from("Environment not found")))?;
+
+    let suite = repository
+        .find_by_id::<Suite>(&suite_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(String::from("Suite not found")))?;
+
+    let suite_execution = execution_queue
+        .queue_suite(environment, suite)
+        .await
+        .map_err(|error| {
+            warn!("{:?}", error);
+            ApiError::Internal(error.to_string())
+        })?;
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&SuiteExecutionDTO::from(suite_execution)),
+        hyper::StatusCode::ACCEPTED,
+    ))
+}
+
+pub async fn executions_for_suite_in_environment(
+    repository: Repository,
+    environment_id: ObjectId,
+    suite_id: ObjectId,
+) -> Result<warp::reply::Json, warp::Rejection> {
+    let suite_executions = repository
+        .find::<SuiteExecution>(doc! { "suiteId": suite_id, "environmentId": environment_id })
+        .await?;
+
+    Ok(warp::reply::json(
+        &suite_executions
+            .into_iter()
+            .map(SuiteExecutionDTO::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateSimulatorData {
     pub name: String,
     #[serde(rename = "imageId")]
     pub image_id: ObjectId,
+    #[serde(rename = "imageVersion")]
+    #[serde(default)]
+    pub image_version: ImageVersion,
     pub configuration: HashMap<String, String>,
 }
 
@@ -168,63 +247,20 @@ pub async fn add_simulator_for_environment(
     environment_id: ObjectId,
     simulator_data: CreateSimulatorData,
 ) -> Result<impl warp::reply::Reply, warp::Rejection> {
-    let simulators_for_environment = repository
-        .find::<Simulator>(doc! {"environmentId": environment_id})
-        .await?;
-
-    for other in &simulators_for_environment {
-        if other.name() == simulator_data.name {
-            return Err(ErrorRejection::reject(
-                "A simulator with the same name exists in this environment",
-                hyper::StatusCode::CONFLICT,
-            ));
-        }
-    }
+    let simulator = Simulator::new(
+        simulator_data.name,
+        environment_id,
+        simulator_data.image_id,
+        simulator_data.image_version,
+        simulator_data.configuration,
+    );
 
-    let port = match simulators_for_environment
-        .iter()
-        .map(|simulator| simulator.port())
-        .max()
-    {
-        Some(port) => port + 1,
-        None => 30000,
-    };
+    simulators_handlers::check_simulator(&repository, None, &simulator).await?;
 
-    let environment = repository
-        .find_by_id::<Environment>(&environment_id)
-        .await?;
-    let image = repository
-        .find_by_id::<Image>(&simulator_data.image_id)
-        .await?;
+    let simulator = repository.create(simulator).await?;
 
-    match (environment, image) {
-        (Some(_), Some(_)) => {
-            let simulator = Simulator::new(
-                simulator_data.name,
-                port,
-                environment_id,
-                simulator_data.image_id,
-                simulator_data.configuration,
-            );
-
-            let simulator = repository.create(simulator).await?;
-
-            Ok(warp::reply::with_status(
-                warp::reply::json(&SimulatorDTO::from(simulator)),
-                hyper::StatusCode::CREATED,
-            ))
-        }
-        (None, None) => Err(ErrorRejection::reject(
-            "Environment and image not found",
-            hyper::StatusCode::NOT_FOUND,
-        )),
-        (None, _) => Err(ErrorRejection::reject(
-            "Environment not found",
-            hyper::StatusCode::NOT_FOUND,
-        )),
-        (_, None) => Err(ErrorRejection::reject(
-            "Image not found",
-            hyper::StatusCode::NOT_FOUND,
-        )),
-    }
+    Ok(warp::reply::with_status(
+        warp::reply::json(&SimulatorDTO::from(simulator)),
+        hyper::StatusCode::CREATED,
+    ))
 }
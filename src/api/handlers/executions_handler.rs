@@ -1,8 +1,344 @@
+use chrono::{DateTime, Local};
+use futures::{SinkExt, StreamExt};
 use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, to_bson, Bson};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio_util::sync::CancellationToken;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+use warp::http::header::CONTENT_TYPE;
 use warp::hyper;
+use warp::ws::Message;
+use warp::Reply;
 
-use crate::{api::error_rejection::ErrorRejection, data::Repository};
-use crate::data::{Execution, ExecutionDTO};
+use crate::{api::error::ApiError, data::Repository};
+use crate::data::{
+    to_bson_date, Environment, Execution, ExecutionDTO, ExecutionStatus, Image, QueryOptions,
+    Scenario, ScenarioPlayingCommand, ScenarioPlayingEvent, Simulator,
+};
+use crate::domain::{
+    self, AnalyticsOptions, ExecutionAnalytics, ExecutionQueue, ExecutionReport, Interval,
+};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+const MAX_TOP: i64 = 100;
+
+#[derive(Debug, Deserialize, Default, Clone, Copy)]
+pub enum ExecutionSort {
+    #[default]
+    #[serde(rename = "timestamp")]
+    Timestamp,
+    /// Executions that did not end have no duration, so they are left out.
+    #[serde(rename = "durationMs")]
+    DurationMs,
+}
+
+impl ExecutionSort {
+    fn field(self) -> &'static str {
+        match self {
+            ExecutionSort::Timestamp => "timestamp",
+            ExecutionSort::DurationMs => "durationMs",
+        }
+    }
+
+    fn value(self, execution: &Execution) -> Option<Bson> {
+        match self {
+            ExecutionSort::Timestamp => Some(Bson::DateTime(to_bson_date(&execution.timestamp()))),
+            ExecutionSort::DurationMs => execution
+                .duration_ms()
+                .map(|duration_ms| Bson::Int64(duration_ms as i64)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListQuery {
+    #[serde(rename = "scenarioId")]
+    pub scenario_id: Option<String>,
+    #[serde(rename = "environmentId")]
+    pub environment_id: Option<String>,
+    /// Keeps the executions of scenarios using the image of the simulator, in its environment.
+    #[serde(rename = "simulatorId")]
+    pub simulator_id: Option<String>,
+    /// Comma separated statuses, any of which matches.
+    pub status: Option<String>,
+    pub from: Option<DateTime<Local>>,
+    pub to: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub sort: ExecutionSort,
+    #[serde(default)]
+    pub order: SortOrder,
+    pub limit: Option<i64>,
+    /// ID of the last execution of the previous page.
+    pub cursor: Option<String>,
+    /// Leaves out the events and variables of the executions.
+    #[serde(default)]
+    pub summary: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExecutionPage {
+    executions: Vec<ExecutionDTO>,
+    #[serde(rename = "nextCursor")]
+    next_cursor: Option<String>,
+}
+
+pub async fn list(
+    repository: Repository,
+    query: ListQuery,
+) -> Result<warp::reply::Json, warp::Rejection> {
+    let mut details = Vec::new();
+
+    let scenario_id = parse_id("scenarioId", query.scenario_id.as_deref(), &mut details);
+    let environment_id = parse_id("environmentId", query.environment_id.as_deref(), &mut details);
+    let simulator_id = parse_id("simulatorId", query.simulator_id.as_deref(), &mut details);
+    let cursor = parse_id("cursor", query.cursor.as_deref(), &mut details);
+    let statuses = parse_statuses(query.status.as_deref(), &mut details);
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        details.push(format!("limit must be between 1 and {}", MAX_PAGE_SIZE));
+    }
+
+    if !details.is_empty() {
+        return Err(ApiError::Validation {
+            message: String::from("Invalid query string"),
+            details,
+        }
+        .into());
+    }
+
+    let mut conditions = Vec::new();
+
+    if let Some(scenario_id) = scenario_id {
+        conditions.push(doc! { "scenarioId": scenario_id });
+    }
+
+    if let Some(environment_id) = environment_id {
+        conditions.push(doc! { "environmentId": environment_id });
+    }
+
+    if let Some(simulator_id) = simulator_id {
+        conditions.extend(simulator_conditions(&repository, &simulator_id).await?);
+    }
+
+    if !statuses.is_empty() {
+        conditions.push(doc! { "status": { "$in": to_bson(&statuses).unwrap() } });
+    }
+
+    if let Some(from) = query.from {
+        conditions.push(doc! { "timestamp": { "$gte": to_bson_date(&from) } });
+    }
+
+    if let Some(to) = query.to {
+        conditions.push(doc! { "timestamp": { "$lte": to_bson_date(&to) } });
+    }
+
+    let field = query.sort.field();
+
+    if let ExecutionSort::DurationMs = query.sort {
+        conditions.push(doc! { "durationMs": { "$ne": Bson::Null } });
+    }
+
+    let (direction, comparison) = match query.order {
+        SortOrder::Asc => (1, "$gt"),
+        SortOrder::Desc => (-1, "$lt"),
+    };
+
+    // Executions sharing the sorted value are told apart by their ID
+    if let Some(cursor) = cursor {
+        let value = repository
+            .find_by_id::<Execution>(&cursor)
+            .await?
+            .and_then(|execution| query.sort.value(&execution))
+            .ok_or_else(|| ApiError::validation("Invalid cursor"))?;
+
+        conditions.push(doc! {
+            "$or": [
+                { field: { comparison: value.clone() } },
+                { field: value, "_id": { comparison: cursor } },
+            ]
+        });
+    }
+
+    let filter = if conditions.is_empty() {
+        doc! {}
+    } else {
+        doc! { "$and": conditions }
+    };
+
+    let mut executions = repository
+        .query::<Execution>(
+            filter,
+            QueryOptions {
+                sort: Some(doc! { field: direction, "_id": direction }),
+                // One more execution than asked tells whether there is a next page
+                limit: Some(limit + 1),
+                projection: query
+                    .summary
+                    .then(|| doc! { "events": 0, "variables": 0 }),
+                ..QueryOptions::default()
+            },
+        )
+        .await?;
+
+    let next_cursor = if executions.len() as i64 > limit {
+        executions.truncate(limit as usize);
+        executions
+            .last()
+            .and_then(|execution| execution.id())
+            .map(ToString::to_string)
+    } else {
+        None
+    };
+
+    Ok(warp::reply::json(&ExecutionPage {
+        executions: executions.into_iter().map(ExecutionDTO::from).collect(),
+        next_cursor,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsQuery {
+    #[serde(rename = "scenarioId")]
+    pub scenario_id: Option<String>,
+    #[serde(rename = "environmentId")]
+    pub environment_id: Option<String>,
+    pub from: Option<DateTime<Local>>,
+    pub to: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub interval: Interval,
+    /// Timezone the periods start in, UTC when missing.
+    pub timezone: Option<String>,
+    pub top: Option<i64>,
+    #[serde(rename = "minFlips")]
+    pub min_flips: Option<u32>,
+}
+
+pub async fn analytics(
+    repository: Repository,
+    query: AnalyticsQuery,
+) -> Result<warp::reply::Json, warp::Rejection> {
+    let mut details = Vec::new();
+    let defaults = AnalyticsOptions::default();
+
+    let options = AnalyticsOptions {
+        scenario_id: parse_id("scenarioId", query.scenario_id.as_deref(), &mut details),
+        environment_id: parse_id("environmentId", query.environment_id.as_deref(), &mut details),
+        from: query.from,
+        to: query.to,
+        interval: query.interval,
+        timezone: query.timezone.unwrap_or(defaults.timezone),
+        top: query.top.unwrap_or(defaults.top),
+        min_flips: query.min_flips.unwrap_or(defaults.min_flips),
+    };
+
+    if !(1..=MAX_TOP).contains(&options.top) {
+        details.push(format!("top must be between 1 and {}", MAX_TOP));
+    }
+
+    if let Err(error) = domain::check_timezone(&options.timezone) {
+        details.push(error);
+    }
+
+    if options.min_flips == 0 {
+        details.push(String::from("minFlips must be positive"));
+    }
+
+    if !details.is_empty() {
+        return Err(ApiError::Validation {
+            message: String::from("Invalid query string"),
+            details,
+        }
+        .into());
+    }
+
+    let analytics = ExecutionAnalytics::compute(&repository, &options)
+        .await
+        .map_err(|error| {
+            warn!("{:?}", error);
+            ApiError::Internal(error.to_string())
+        })?;
+
+    Ok(warp::reply::json(&analytics))
+}
+
+/// A simulator is used by the executions of scenarios having steps on any version of its image,
+/// in its environment.
+async fn simulator_conditions(
+    repository: &Repository,
+    simulator_id: &ObjectId,
+) -> Result<Vec<mongodb::bson::Document>, warp::Rejection> {
+    let simulator = repository
+        .find_by_id::<Simulator>(simulator_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(String::from("Simulator not found")))?;
+
+    let image_ids = match repository.find_by_id::<Image>(simulator.image_id()).await? {
+        Some(image) => domain::versions(repository, &image.tag().name)
+            .await
+            .map_err(|error| {
+                warn!("{:?}", error);
+                ApiError::Internal(format!("Couldn't find image: {:?}", error))
+            })?
+            .iter()
+            .filter_map(|version| version.id().copied())
+            .collect(),
+        None => vec![*simulator.image_id()],
+    };
+
+    let scenario_ids = repository
+        .find::<Scenario>(doc! { "steps.imageId": { "$in": image_ids } })
+        .await?
+        .iter()
+        .filter_map(|scenario| scenario.id().copied())
+        .collect::<Vec<_>>();
+
+    Ok(vec![
+        doc! { "scenarioId": { "$in": scenario_ids } },
+        doc! { "environmentId": simulator.environment_id() },
+    ])
+}
+
+fn parse_id(name: &str, value: Option<&str>, details: &mut Vec<String>) -> Option<ObjectId> {
+    let value = value?;
+
+    match ObjectId::parse_str(value) {
+        Ok(id) => Some(id),
+        Err(_) => {
+            details.push(format!("{} is not a valid ID: {}", name, value));
+            None
+        }
+    }
+}
+
+fn parse_statuses(value: Option<&str>, details: &mut Vec<String>) -> Vec<ExecutionStatus> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|status| !status.is_empty())
+        .filter_map(|status| {
+            match serde_json::from_value(Value::String(status.to_owned())) {
+                Ok(status) => Some(status),
+                Err(_) => {
+                    details.push(format!("Unknown status: {}", status));
+                    None
+                }
+            }
+        })
+        .collect()
+}
 
 pub async fn find_by_id(
     repository: Repository,
@@ -12,9 +348,157 @@ pub async fn find_by_id(
 
     match option_execution {
         Some(execution) => Ok(warp::reply::json(&ExecutionDTO::from(execution))),
-        None => Err(ErrorRejection::reject(
-            "Execution not found",
-            hyper::StatusCode::NOT_FOUND,
-        )),
+        None => Err(ApiError::NotFound(String::from("Execution not found")).into()),
+    }
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Junit,
+    #[default]
+    Json,
+    Html,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportQuery {
+    #[serde(default)]
+    pub format: ReportFormat,
+}
+
+pub async fn report(
+    repository: Repository,
+    execution_id: ObjectId,
+    query: ReportQuery,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let execution = repository
+        .find_by_id::<Execution>(&execution_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(String::from("Execution not found")))?;
+
+    // Reports of executions whose scenario or environment were removed fall back to their IDs
+    let scenario = repository
+        .find_by_id::<Scenario>(execution.scenario_id())
+        .await?;
+    let environment = repository
+        .find_by_id::<Environment>(execution.environment_id())
+        .await?;
+
+    let report = ExecutionReport::new(&execution, scenario.as_ref(), environment.as_ref());
+
+    let (body, content_type) = match query.format {
+        ReportFormat::Junit => (report.to_junit(), "application/xml"),
+        ReportFormat::Json => return Ok(warp::reply::json(&report).into_response()),
+        ReportFormat::Html => (report.to_html(), "text/html; charset=utf-8"),
+    };
+
+    Ok(warp::reply::with_header(body, CONTENT_TYPE, content_type).into_response())
+}
+
+pub async fn attach(
+    repository: Repository,
+    execution_id: ObjectId,
+    web_socket: warp::ws::Ws,
+    execution_queue: ExecutionQueue,
+) -> Result<impl warp::reply::Reply, warp::Rejection> {
+    if repository
+        .find_by_id::<Execution>(&execution_id)
+        .await?
+        .is_none()
+    {
+        return Err(ApiError::NotFound(String::from("Execution not found")).into());
+    }
+
+    Ok(web_socket.on_upgrade(move |web_socket| async move {
+        match execution_queue.registry().subscribe(&execution_id) {
+            Some((rx, cancellation)) => forward_events(web_socket, rx, cancellation).await,
+            None => replay_events(web_socket, &repository, &execution_id).await,
+        }
+    }))
+}
+
+pub async fn cancel(
+    repository: Repository,
+    execution_id: ObjectId,
+    execution_queue: ExecutionQueue,
+) -> Result<impl warp::reply::Reply, warp::Rejection> {
+    if repository
+        .find_by_id::<Execution>(&execution_id)
+        .await?
+        .is_none()
+    {
+        return Err(ApiError::NotFound(String::from("Execution not found")).into());
+    }
+
+    if !execution_queue.registry().cancel(&execution_id) {
+        return Err(ApiError::Conflict(String::from("Execution is not running")).into());
+    }
+
+    Ok(warp::reply::with_status(
+        warp::reply(),
+        hyper::StatusCode::ACCEPTED,
+    ))
+}
+
+/// Sends the events of an execution to the client, and cancels the execution when asked to.
+pub async fn forward_events(
+    mut web_socket: warp::ws::WebSocket,
+    mut rx: UnboundedReceiver<ScenarioPlayingEvent>,
+    cancellation: CancellationToken,
+) {
+    loop {
+        tokio::select! {
+            event = rx.recv() => {
+                let Some(event) = event else { break };
+
+                if let Err(err) = web_socket.send(event_message(&event)).await {
+                    warn!("{:?}", err);
+                }
+            }
+            Some(Ok(message)) = web_socket.next() => {
+                if let Some(ScenarioPlayingCommand::Cancel) = parse_command(&message) {
+                    cancellation.cancel();
+                }
+            }
+        }
+    }
+
+    web_socket.close().await.ok();
+}
+
+/// Sends the events of an execution that is over, all of which were saved.
+pub async fn replay_events(
+    mut web_socket: warp::ws::WebSocket,
+    repository: &Repository,
+    execution_id: &ObjectId,
+) {
+    let events = match repository.find_by_id::<Execution>(execution_id).await {
+        Ok(Some(execution)) => execution.events().clone(),
+        _ => Vec::new(),
+    };
+
+    for event in &events {
+        if let Err(err) = web_socket.send(event_message(event)).await {
+            warn!("{:?}", err);
+        }
+    }
+
+    web_socket.close().await.ok();
+}
+
+fn event_message(event: &ScenarioPlayingEvent) -> Message {
+    Message::text(serde_json::to_string(event).unwrap_or_default())
+}
+
+fn parse_command(message: &Message) -> Option<ScenarioPlayingCommand> {
+    let text = message.to_str().ok()?;
+
+    match serde_json::from_str(text) {
+        Ok(command) => Some(command),
+        Err(error) => {
+            warn!("Ignoring invalid command {:?}: {:?}", text, error);
+            None
+        }
     }
 }
@@ -1,19 +1,29 @@
 use std::collections::HashMap;
-use std::io::Read;
+use std::convert::Infallible;
 use std::sync::Arc;
 
+use bollard::auth::DockerCredentials;
 use bollard::Docker;
 use bytes::BufMut;
 use futures::TryStreamExt;
 use mongodb::bson::doc;
 use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
 use tracing::warn;
+use warp::http::header::{HeaderValue, CONTENT_TYPE};
+use warp::hyper;
 use warp::multipart::{FormData, Part};
-use warp::{hyper, Buf};
+use warp::Reply;
 
-use crate::api::error_rejection::ErrorRejection;
-use crate::data::{Image, ImageDTO, Repository};
-use crate::domain::DockerImage;
+use crate::api::error::ApiError;
+use crate::data::{
+    Command, CommandsDiff, Image, ImageBuildEvent, ImageDTO, ImageStatus, ImageVersion, Repository,
+    Scenario, Simulator,
+};
+use crate::domain::{self, DockerImage, ImageBuildQueue, ImageSource};
+
+const NDJSON: &str = "application/x-ndjson";
 
 pub async fn list(repository: Repository) -> Result<warp::reply::Json, warp::Rejection> {
     let images = repository.list::<Image>().await?;
@@ -25,148 +35,440 @@ pub async fn list(repository: Repository) -> Result<warp::reply::Json, warp::Rej
 
 pub async fn create(
     repository: Repository,
-    docker: Arc<Docker>,
+    build_queue: ImageBuildQueue,
+    accept: Option<String>,
     form_data: FormData,
-) -> Result<impl warp::reply::Reply, warp::Rejection> {
+) -> Result<warp::reply::Response, warp::Rejection> {
     let mut parts: HashMap<String, Part> = form_data
         .map_ok(|part| (String::from(part.name()), part))
         .try_collect()
         .await
         .map_err(|error| {
             warn!("{:?}", error);
-            ErrorRejection::reject(
-                error.to_string().as_str(),
-                hyper::StatusCode::INTERNAL_SERVER_ERROR,
-            )
+            ApiError::Internal(error.to_string())
         })?;
 
-    if !parts.contains_key("image") {
-        return Err(ErrorRejection::reject(
-            "Missing image file",
-            hyper::StatusCode::BAD_REQUEST,
-        ));
-    }
-
-    if !parts.contains_key("image_data") {
-        return Err(ErrorRejection::reject(
-            "Missing image data",
-            hyper::StatusCode::BAD_REQUEST,
-        ));
-    }
-
-    let image_data_part = parts.remove("image_data").unwrap();
+    let image_data_part = parts
+        .remove("image_data")
+        .ok_or_else(|| ApiError::validation("Missing image data"))?;
 
     let image = parse_part_to_image(image_data_part).await?;
 
+    domain::check_version(&image.tag().version).map_err(ApiError::validation)?;
+    check_command_schemas(&image)?;
+    mongodb::bson::Document::try_from(image.clone()).map_err(ApiError::unsavable)?;
+
     let already_existing_image = repository
         .find::<Image>(doc! {"tag": {"name": &image.tag().name, "version": &image.tag().version}})
         .await?;
 
     if !already_existing_image.is_empty() {
-        return Err(ErrorRejection::reject(
-            "Image already exists",
-            hyper::StatusCode::CONFLICT,
-        ));
+        return Err(ApiError::Conflict(String::from("Image already exists")).into());
     }
 
-    let image_file_part = parts.remove("image").unwrap();
+    let source = parse_parts_to_source(parts).await?;
 
-    let image_bytes = image_file_part
-        .stream()
-        .try_fold(Vec::new(), |mut acc, chunk| {
-            acc.put(chunk);
-            async move { Ok(acc) }
-        })
-        .await
-        .map_err(|error| {
-            warn!("{:?}", error);
-            ErrorRejection::reject("Failed to read image file", hyper::StatusCode::BAD_REQUEST)
-        })?;
+    let image = repository.create(image.building()).await?;
 
-    DockerImage::create(docker, image.tag().to_owned(), image_bytes)
-        .await
-        .map_err(|error| {
-            warn!("{:?}", error);
-            ErrorRejection::reject(&error.to_string(), hyper::StatusCode::INTERNAL_SERVER_ERROR)
-        })?;
+    // Clients asking for newline delimited JSON follow the build as it happens
+    if accept.is_some_and(|accept| accept.contains(NDJSON)) {
+        let (tx, rx) = mpsc::unbounded_channel();
 
-    let image = repository.create(image).await?;
+        build_queue.queue(image, source, Some(tx));
+
+        return Ok(ndjson_response(rx));
+    }
+
+    build_queue.queue(image.clone(), source, None);
 
     Ok(warp::reply::with_status(
         warp::reply::json(&ImageDTO::from(image)),
-        hyper::StatusCode::CREATED,
-    ))
+        hyper::StatusCode::ACCEPTED,
+    )
+    .into_response())
 }
 
-async fn parse_part_to_image(image_data_part: Part) -> Result<Image, warp::Rejection> {
-    let image_data = image_data_part
-        .stream()
-        .try_fold(String::new(), |mut acc, chunk| {
-            chunk
-                .chunk()
-                .read_to_string(&mut acc)
-                .expect("Failed to read image data");
+fn ndjson_response(rx: UnboundedReceiver<ImageBuildEvent>) -> warp::reply::Response {
+    let lines = futures::stream::unfold(rx, |mut rx| async move {
+        let event = rx.recv().await?;
+        let line = format!("{}\n", serde_json::to_string(&event).unwrap_or_default());
+
+        Some((Ok::<_, Infallible>(line), rx))
+    });
+
+    let mut response = warp::reply::Response::new(hyper::Body::wrap_stream(lines));
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(NDJSON));
+
+    response
+}
+
+fn check_command_schemas(image: &Image) -> Result<(), ApiError> {
+    let details = image
+        .commands()
+        .iter()
+        .flat_map(|command| {
+            [&command.arguments_schema, &command.response_schema]
+                .into_iter()
+                .flatten()
+                .filter_map(|schema| domain::check_schema(schema).err())
+                .map(|error| format!("Command {}: {}", command.path, error))
+        })
+        .collect::<Vec<_>>();
+
+    if details.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::Validation {
+            message: String::from("Invalid command schemas"),
+            details,
+        })
+    }
+}
+
+/// The Docker image is built from an `image` build context, loaded from an `archive` made by
+/// `docker save`, or pulled from the registry of a `reference`.
+async fn parse_parts_to_source(
+    mut parts: HashMap<String, Part>,
+) -> Result<ImageSource, warp::Rejection> {
+    let sources = ["image", "archive", "reference"]
+        .into_iter()
+        .filter_map(|name| parts.remove(name).map(|part| (name, part)))
+        .collect::<Vec<_>>();
+
+    let [(name, part)]: [(&str, Part); 1] = sources.try_into().map_err(|_| {
+        ApiError::validation("Expected exactly one of the image, archive or reference parts")
+    })?;
+
+    let bytes = read_part(part).await?;
+
+    match name {
+        "image" => Ok(ImageSource::BuildContext(bytes)),
+        "archive" => Ok(ImageSource::Archive(bytes)),
+        _ => {
+            let reference = String::from_utf8(bytes)
+                .map_err(|_| ApiError::validation("Image reference is not valid UTF-8"))?;
+
+            let username = read_text_part(&mut parts, "registry_username").await?;
+            let password = read_text_part(&mut parts, "registry_password").await?;
+            let server_address = read_text_part(&mut parts, "registry_server").await?;
+
+            let credentials = username.map(|username| DockerCredentials {
+                username: Some(username),
+                password,
+                serveraddress: server_address,
+                ..Default::default()
+            });
+
+            Ok(ImageSource::Registry {
+                reference: reference.trim().to_owned(),
+                credentials,
+            })
+        }
+    }
+}
+
+async fn read_text_part(
+    parts: &mut HashMap<String, Part>,
+    name: &str,
+) -> Result<Option<String>, ApiError> {
+    match parts.remove(name) {
+        Some(part) => Ok(Some(
+            String::from_utf8_lossy(&read_part(part).await?).into_owned(),
+        )),
+        None => Ok(None),
+    }
+}
+
+async fn read_part(part: Part) -> Result<Vec<u8>, ApiError> {
+    part.stream()
+        .try_fold(Vec::new(), |mut acc, chunk| {
+            acc.put(chunk);
             async move { Ok(acc) }
         })
         .await
         .map_err(|error| {
             warn!("{:?}", error);
-            ErrorRejection::reject(
-                &format!("Invalid image file: {:?}", error),
-                hyper::StatusCode::BAD_REQUEST,
-            )
-        })?;
+            ApiError::validation(format!("Failed to read part: {:?}", error))
+        })
+}
+
+async fn parse_part_to_image(image_data_part: Part) -> Result<Image, warp::Rejection> {
+    let image_data = read_part(image_data_part).await?;
+
+    let image_data = String::from_utf8(image_data).map_err(|error| {
+        warn!("{:?}", error);
+        ApiError::validation(format!("Image data is not valid UTF-8: {:?}", error))
+    })?;
 
     let image = serde_json::from_str(&image_data).map_err(|error| {
         warn!("{:?}", error);
-        ErrorRejection::reject(
-            &format!("Couldn't parse image data: {:?}", error),
-            hyper::StatusCode::BAD_REQUEST,
-        )
+        ApiError::validation(format!("Couldn't parse image data: {:?}", error))
     })?;
 
     Ok(image)
 }
 
+pub async fn build_log(
+    repository: Repository,
+    image_id: ObjectId,
+) -> Result<warp::reply::Json, warp::Rejection> {
+    match repository.find_by_id::<Image>(&image_id).await? {
+        Some(image) => Ok(warp::reply::json(image.build_log())),
+        None => Err(ApiError::NotFound(String::from("Image not found")).into()),
+    }
+}
+
 pub async fn find_by_id(
     repository: Repository,
     image_id: ObjectId,
 ) -> Result<warp::reply::Json, warp::Rejection> {
     match repository.find_by_id::<Image>(&image_id).await? {
         Some(image) => Ok(warp::reply::json(&ImageDTO::from(image))),
-        None => Err(ErrorRejection::reject(
-            "Image not found",
-            hyper::StatusCode::NOT_FOUND,
-        )),
+        None => Err(ApiError::NotFound(String::from("Image not found")).into()),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateImageData {
+    pub description: String,
+    pub commands: Vec<Command>,
+}
+
+pub async fn update(
+    repository: Repository,
+    image_id: ObjectId,
+    image_data: UpdateImageData,
+) -> Result<warp::reply::Json, warp::Rejection> {
+    let image = repository
+        .find_by_id::<Image>(&image_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(String::from("Image not found")))?;
+
+    // The build would overwrite the commands once it ends
+    if image.status() == ImageStatus::Building {
+        return Err(ApiError::Conflict(String::from("Image is still building")).into());
+    }
+
+    let image = image.edit(image_data.description, image_data.commands);
+
+    check_command_schemas(&image)?;
+    check_used_commands_kept(&repository, &image_id, &image).await?;
+    let document = mongodb::bson::Document::try_from(image.clone()).map_err(ApiError::unsavable)?;
+
+    repository.update::<Image>(&image_id, document).await?;
+
+    Ok(warp::reply::json(&ImageDTO::from(image)))
+}
+
+/// Returns the ids of every version of the image name when the image is its latest ready
+/// version, as `Latest` references to any of these versions resolve to it.
+async fn latest_reference_ids(
+    repository: &Repository,
+    image_id: &ObjectId,
+    image: &Image,
+) -> Result<Vec<ObjectId>, warp::Rejection> {
+    let versions = domain::versions(repository, &image.tag().name)
+        .await
+        .map_err(|error| {
+            warn!("{:?}", error);
+            ApiError::Internal(format!("Couldn't find image versions: {:?}", error))
+        })?;
+
+    let latest_id = versions
+        .iter()
+        .rev()
+        .find(|version| version.status() == ImageStatus::Ready)
+        .and_then(Image::id);
+
+    if latest_id != Some(image_id) {
+        return Ok(vec![]);
+    }
+
+    Ok(versions.iter().filter_map(Image::id).cloned().collect())
+}
+
+/// Scenarios would break if a command they use was removed from the image.
+async fn check_used_commands_kept(
+    repository: &Repository,
+    image_id: &ObjectId,
+    image: &Image,
+) -> Result<(), warp::Rejection> {
+    let latest_ids = latest_reference_ids(repository, image_id, image).await?;
+    let scenarios = repository
+        .find::<Scenario>(doc! {
+            "$or": [
+                { "steps.imageId": image_id },
+                { "steps": { "$elemMatch": {
+                    "imageId": { "$in": latest_ids.clone() },
+                    "imageVersion": "latest",
+                } } },
+            ]
+        })
+        .await?;
+
+    let mut missing_commands = scenarios
+        .iter()
+        .flat_map(|scenario| {
+            scenario
+                .steps()
+                .iter()
+                .filter(|step| {
+                    &step.image_id == image_id
+                        || (step.image_version == ImageVersion::Latest
+                            && latest_ids.contains(&step.image_id))
+                })
+                .filter(|step| {
+                    !image
+                        .commands()
+                        .iter()
+                        .any(|command| command.path == step.command.path)
+                })
+                .map(|step| format!("{} (scenario {})", step.command.path, scenario.name()))
+        })
+        .collect::<Vec<_>>();
+
+    if missing_commands.is_empty() {
+        return Ok(());
     }
+
+    missing_commands.sort();
+    missing_commands.dedup();
+
+    Err(ApiError::Conflict(format!(
+        "Commands are still used: {}",
+        missing_commands.join(", ")
+    ))
+    .into())
 }
 
+pub async fn remove(
+    repository: Repository,
+    image_id: ObjectId,
+    docker: Arc<Docker>,
+) -> Result<warp::reply::Json, warp::Rejection> {
+    let image = repository
+        .find_by_id::<Image>(&image_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(String::from("Image not found")))?;
+
+    // The build would create the Docker image again once it ends
+    if image.status() == ImageStatus::Building {
+        return Err(ApiError::Conflict(String::from("Image is still building")).into());
+    }
+
+    check_image_unused(&repository, &image_id, &image).await?;
+
+    DockerImage::from(image.tag().to_owned())
+        .delete(docker)
+        .await
+        .map_err(|error| {
+            warn!("{:?}", error);
+            ApiError::Internal(error.to_string())
+        })?;
+
+    match repository.remove::<Image>(&image_id).await {
+        Ok(image) => Ok(warp::reply::json(&ImageDTO::from(image))),
+        Err(_) => Err(ApiError::NotFound(String::from("Image not found")).into()),
+    }
+}
+
+/// Images still referenced by scenarios or simulators, pinned or as their latest version, cannot be
+/// removed, as these would break.
+async fn check_image_unused(
+    repository: &Repository,
+    image_id: &ObjectId,
+    image: &Image,
+) -> Result<(), warp::Rejection> {
+    let latest_ids = latest_reference_ids(repository, image_id, image).await?;
+    let scenarios = repository
+        .find::<Scenario>(doc! {
+            "$or": [
+                { "steps.imageId": image_id },
+                { "steps": { "$elemMatch": {
+                    "imageId": { "$in": latest_ids.clone() },
+                    "imageVersion": "latest",
+                } } },
+            ]
+        })
+        .await?;
+    let simulators = repository
+        .find::<Simulator>(doc! {
+            "$or": [
+                { "imageId": image_id },
+                { "imageId": { "$in": latest_ids }, "imageVersion": "latest" },
+            ]
+        })
+        .await?;
+
+    if scenarios.is_empty() && simulators.is_empty() {
+        return Ok(());
+    }
+
+    let users = scenarios
+        .iter()
+        .map(|scenario| format!("scenario {}", scenario.name()))
+        .chain(
+            simulators
+                .iter()
+                .map(|simulator| format!("simulator {}", simulator.name())),
+        )
+        .collect::<Vec<_>>();
+
+    Err(ApiError::Conflict(format!("Image is still used by {}", users.join(", "))).into())
+}
+
+/// Lists every version of the image, from the oldest to the latest.
 pub async fn find_by_name(
     repository: Repository,
     image_name: String,
 ) -> Result<warp::reply::Json, warp::Rejection> {
-    match repository
-        .find::<Image>(doc! {"tag": {"name": &image_name } })
+    let images = domain::versions(&repository, &image_name)
         .await
-    {
-        Ok(images) => {
-            if images.is_empty() {
-                Err(ErrorRejection::reject(
-                    "Image not found",
-                    hyper::StatusCode::NOT_FOUND,
-                ))
-            } else {
-                Ok(warp::reply::json(
-                    &images.into_iter().map(ImageDTO::from).collect::<Vec<_>>(),
-                ))
-            }
-        }
-        Err(error) => {
+        .map_err(|error| {
             warn!("{:?}", error);
-            Err(ErrorRejection::reject(
-                &format!("Couldn't find image: {:?}", error),
-                hyper::StatusCode::INTERNAL_SERVER_ERROR,
-            ))
-        }
+            ApiError::Internal(format!("Couldn't find image: {:?}", error))
+        })?;
+
+    if images.is_empty() {
+        return Err(ApiError::NotFound(String::from("Image not found")).into());
     }
+
+    Ok(warp::reply::json(
+        &images.into_iter().map(ImageDTO::from).collect::<Vec<_>>(),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffQuery {
+    pub from: String,
+    pub to: String,
+}
+
+pub async fn diff_versions(
+    repository: Repository,
+    image_name: String,
+    query: DiffQuery,
+) -> Result<warp::reply::Json, warp::Rejection> {
+    let from = find_version(&repository, &image_name, &query.from).await?;
+    let to = find_version(&repository, &image_name, &query.to).await?;
+
+    Ok(warp::reply::json(&CommandsDiff::between(
+        from.commands(),
+        to.commands(),
+    )))
+}
+
+async fn find_version(
+    repository: &Repository,
+    image_name: &str,
+    version: &str,
+) -> Result<Image, warp::Rejection> {
+    repository
+        .find_one::<Image>(doc! { "tag.name": image_name, "tag.version": version })
+        .await?
+        .ok_or_else(|| {
+            ApiError::NotFound(format!("Version {} of {} not found", version, image_name)).into()
+        })
 }
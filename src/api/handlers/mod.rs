@@ -3,3 +3,4 @@ pub mod executions_handler;
 pub mod images_handlers;
 pub mod scenarios_handlers;
 pub mod simulators_handlers;
+pub mod suites_handlers;
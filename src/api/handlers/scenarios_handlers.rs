@@ -1,9 +1,13 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
 use mongodb::bson::{doc, oid::ObjectId};
 use warp::hyper;
 
 use crate::data::ScenarioDTO;
+use crate::domain;
 use crate::{
-    api::error_rejection::ErrorRejection,
+    api::error::ApiError,
     data::{Repository, Scenario},
 };
 
@@ -27,12 +31,12 @@ pub async fn create(
         .await?;
 
     if !already_existing_scenario.is_empty() {
-        return Err(ErrorRejection::reject(
-            "Scenario already exists",
-            hyper::StatusCode::CONFLICT,
-        ));
+        return Err(ApiError::Conflict(String::from("Scenario already exists")).into());
     }
 
+    check_steps(&repository, &scenario).await?;
+    mongodb::bson::Document::try_from(scenario.clone()).map_err(ApiError::unsavable)?;
+
     let scenario = repository.create(scenario).await?;
 
     Ok(warp::reply::with_status(
@@ -47,10 +51,7 @@ pub async fn find_by_id(
 ) -> Result<warp::reply::Json, warp::Rejection> {
     match repository.find_by_id::<Scenario>(&scenario_id).await? {
         Some(scenario) => Ok(warp::reply::json(&ScenarioDTO::from(scenario))),
-        None => Err(ErrorRejection::reject(
-            "Could not find scenario",
-            hyper::StatusCode::NOT_FOUND,
-        )),
+        None => Err(ApiError::NotFound(String::from("Could not find scenario")).into()),
     }
 }
 
@@ -59,15 +60,12 @@ pub async fn update(
     scenario_id: ObjectId,
     scenario: Scenario,
 ) -> Result<warp::reply::Json, warp::Rejection> {
-    match repository
-        .update::<Scenario>(&scenario_id, scenario.into())
-        .await
-    {
+    check_steps(&repository, &scenario).await?;
+    let scenario = mongodb::bson::Document::try_from(scenario).map_err(ApiError::unsavable)?;
+
+    match repository.update::<Scenario>(&scenario_id, scenario).await {
         Ok(scenario) => Ok(warp::reply::json(&ScenarioDTO::from(scenario))),
-        Err(_) => Err(ErrorRejection::reject(
-            "Could not find scenario",
-            hyper::StatusCode::NOT_FOUND,
-        )),
+        Err(_) => Err(ApiError::NotFound(String::from("Could not find scenario")).into()),
     }
 }
 
@@ -77,9 +75,79 @@ pub async fn remove(
 ) -> Result<warp::reply::Json, warp::Rejection> {
     match repository.remove::<Scenario>(&scenario_id).await {
         Ok(scenario) => Ok(warp::reply::json(&ScenarioDTO::from(scenario))),
-        Err(_) => Err(ErrorRejection::reject(
-            "Could not find scenario",
-            hyper::StatusCode::NOT_FOUND,
-        )),
+        Err(_) => Err(ApiError::NotFound(String::from("Could not find scenario")).into()),
+    }
+}
+
+/// Checks that every step references an existing image exposing its command, that its arguments
+/// match the schema declared for that command, and that its expectations and captures compile.
+async fn check_steps(repository: &Repository, scenario: &Scenario) -> Result<(), warp::Rejection> {
+    let mut images = HashMap::new();
+    let mut details = domain::check_captures(scenario.steps())
+        .err()
+        .unwrap_or_default();
+
+    for (i, step) in scenario.steps().iter().enumerate() {
+        if let Err(errors) = domain::check_expectations(&step.expectations) {
+            details.extend(
+                errors
+                    .into_iter()
+                    .map(|error| format!("Step {}: {}", i + 1, error)),
+            );
+        }
+
+        let key = (step.image_id, step.image_version);
+
+        // Steps following the latest version are checked against the current latest version
+        if let Entry::Vacant(entry) = images.entry(key) {
+            let image = domain::resolve_image(repository, &step.image_id, step.image_version)
+                .await
+                .map_err(|error| ApiError::Internal(error.to_string()))?;
+
+            entry.insert(image);
+        }
+
+        let Some(image) = &images[&key] else {
+            details.push(format!(
+                "Step {}: image {} does not exist",
+                i + 1,
+                step.image_id
+            ));
+            continue;
+        };
+
+        let Some(command) = image
+            .commands()
+            .iter()
+            .find(|command| command.path == step.command.path)
+        else {
+            details.push(format!(
+                "Step {}: image {} has no command {}",
+                i + 1,
+                image.tag().as_meta(),
+                step.command.path
+            ));
+            continue;
+        };
+
+        if let Some(schema) = &command.arguments_schema {
+            if let Err(errors) = domain::validate_arguments(schema, &step.arguments) {
+                details.extend(
+                    errors
+                        .into_iter()
+                        .map(|error| format!("Step {}: {}", i + 1, error)),
+                );
+            }
+        }
+    }
+
+    if details.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::Validation {
+            message: String::from("Invalid steps"),
+            details,
+        }
+        .into())
     }
 }
@@ -1,23 +1,25 @@
+use mongodb::bson::doc;
 use mongodb::bson::oid::ObjectId;
-use warp::hyper;
 
-use crate::api::error_rejection::ErrorRejection;
-use crate::data::{Repository, Simulator, SimulatorDTO};
+use crate::api::error::ApiError;
+use crate::data::{DataError, Environment, Image, Repository, Simulator, SimulatorDTO};
 
 pub async fn update(
     repository: Repository,
     simulator_id: ObjectId,
     simulator: Simulator,
 ) -> Result<warp::reply::Json, warp::Rejection> {
+    check_simulator(&repository, Some(&simulator_id), &simulator).await?;
+
     match repository
         .update::<Simulator>(&simulator_id, simulator.into())
         .await
     {
         Ok(simulator) => Ok(warp::reply::json(&SimulatorDTO::from(simulator))),
-        Err(_) => Err(ErrorRejection::reject(
-            "Failed to update simulator",
-            hyper::StatusCode::INTERNAL_SERVER_ERROR,
-        )),
+        Err(DataError::NotFound) => {
+            Err(ApiError::NotFound(String::from("Could not find simulator")).into())
+        }
+        Err(error) => Err(ApiError::DatabaseError(error).into()),
     }
 }
 
@@ -27,9 +29,43 @@ pub async fn remove(
 ) -> Result<warp::reply::Json, warp::Rejection> {
     match repository.remove::<Simulator>(&simulator_id).await {
         Ok(simulator) => Ok(warp::reply::json(&SimulatorDTO::from(simulator))),
-        Err(_) => Err(ErrorRejection::reject(
-            "Failed to remove simulator",
-            hyper::StatusCode::INTERNAL_SERVER_ERROR,
-        )),
+        Err(DataError::NotFound) => {
+            Err(ApiError::NotFound(String::from("Could not find simulator")).into())
+        }
+        Err(error) => Err(ApiError::DatabaseError(error).into()),
+    }
+}
+
+/// Checks that the simulator's name is unique within its environment, other than for the
+/// simulator itself, and that its environment and image exist.
+pub async fn check_simulator(
+    repository: &Repository,
+    simulator_id: Option<&ObjectId>,
+    simulator: &Simulator,
+) -> Result<(), ApiError> {
+    let simulators_for_environment = repository
+        .find::<Simulator>(doc! {"environmentId": simulator.environment_id()})
+        .await?;
+
+    for other in &simulators_for_environment {
+        if other.name() == simulator.name() && other.id() != simulator_id {
+            return Err(ApiError::Conflict(String::from(
+                "A simulator with the same name exists in this environment",
+            )));
+        }
+    }
+
+    let environment = repository
+        .find_by_id::<Environment>(simulator.environment_id())
+        .await?;
+    let image = repository.find_by_id::<Image>(simulator.image_id()).await?;
+
+    match (environment, image) {
+        (Some(_), Some(_)) => Ok(()),
+        (None, None) => Err(ApiError::NotFound(String::from(
+            "Environment and image not found",
+        ))),
+        (None, _) => Err(ApiError::NotFound(String::from("Environment not found"))),
+        (_, None) => Err(ApiError::NotFound(String::from("Image not found"))),
     }
 }
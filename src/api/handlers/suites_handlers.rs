@@ -0,0 +1,93 @@
+use mongodb::bson::{doc, oid::ObjectId};
+use warp::hyper;
+
+use crate::data::{Scenario, SuiteDTO};
+use crate::{
+    api::error::ApiError,
+    data::{Repository, Suite},
+};
+
+pub async fn list(repository: Repository) -> Result<warp::reply::Json, warp::Rejection> {
+    let suites = repository.list::<Suite>().await?;
+
+    Ok(warp::reply::json(
+        &suites.into_iter().map(SuiteDTO::from).collect::<Vec<_>>(),
+    ))
+}
+
+pub async fn create(
+    repository: Repository,
+    suite: Suite,
+) -> Result<impl warp::reply::Reply, warp::Rejection> {
+    let already_existing_suite = repository
+        .find::<Suite>(doc! {"name": suite.name() })
+        .await?;
+
+    if !already_existing_suite.is_empty() {
+        return Err(ApiError::Conflict(String::from("Suite already exists")).into());
+    }
+
+    check_scenarios_exist(&repository, &suite).await?;
+
+    let suite = repository.create(suite).await?;
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&SuiteDTO::from(suite)),
+        hyper::StatusCode::CREATED,
+    ))
+}
+
+pub async fn find_by_id(
+    repository: Repository,
+    suite_id: ObjectId,
+) -> Result<warp::reply::Json, warp::Rejection> {
+    match repository.find_by_id::<Suite>(&suite_id).await? {
+        Some(suite) => Ok(warp::reply::json(&SuiteDTO::from(suite))),
+        None => Err(ApiError::NotFound(String::from("Could not find suite")).into()),
+    }
+}
+
+pub async fn update(
+    repository: Repository,
+    suite_id: ObjectId,
+    suite: Suite,
+) -> Result<warp::reply::Json, warp::Rejection> {
+    check_scenarios_exist(&repository, &suite).await?;
+
+    match repository.update::<Suite>(&suite_id, suite.into()).await {
+        Ok(suite) => Ok(warp::reply::json(&SuiteDTO::from(suite))),
+        Err(_) => Err(ApiError::NotFound(String::from("Could not find suite")).into()),
+    }
+}
+
+pub async fn remove(
+    repository: Repository,
+    suite_id: ObjectId,
+) -> Result<warp::reply::Json, warp::Rejection> {
+    match repository.remove::<Suite>(&suite_id).await {
+        Ok(suite) => Ok(warp::reply::json(&SuiteDTO::from(suite))),
+        Err(_) => Err(ApiError::NotFound(String::from("Could not find suite")).into()),
+    }
+}
+
+async fn check_scenarios_exist(
+    repository: &Repository,
+    suite: &Suite,
+) -> Result<(), warp::Rejection> {
+    let scenarios = repository
+        .find::<Scenario>(doc! { "_id": { "$in": suite.scenario_ids() } })
+        .await?;
+
+    for scenario_id in suite.scenario_ids() {
+        if !scenarios
+            .iter()
+            .any(|scenario| scenario.id() == Some(scenario_id))
+        {
+            return Err(
+                ApiError::validation(format!("Could not find scenario {}", scenario_id)).into(),
+            );
+        }
+    }
+
+    Ok(())
+}
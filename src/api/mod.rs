@@ -3,43 +3,130 @@ use std::{convert::Infallible, sync::Arc};
 use bollard::Docker;
 use mongodb::Database;
 use serde_json::json;
-use warp::Filter;
+use warp::body::BodyDeserializeError;
+use warp::hyper::StatusCode;
+use warp::{reject, Filter};
 
 use crate::api::routes::{
     environments_routes, executions_routes, images_routes, scenarios_routes, simulators_routes,
+    suites_routes,
 };
 
-use self::error_rejection::ErrorRejection;
+use crate::data::Repository;
+use crate::domain::{ExecutionQueue, ExecutionSettings, ImageBuildQueue};
+
+use self::error::ApiError;
 
 mod error;
-mod error_rejection;
 mod handlers;
 mod routes;
 
 pub fn routes(
     database: Arc<Database>,
     docker: Arc<Docker>,
+    execution_settings: ExecutionSettings,
+    max_concurrent_builds: usize,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
-    images_routes(Arc::clone(&database), Arc::clone(&docker))
-        .or(environments_routes(Arc::clone(&database), docker))
+    let execution_queue = ExecutionQueue::new(
+        Arc::clone(&docker),
+        Repository::new(Arc::clone(&database)),
+        execution_settings,
+    );
+
+    let build_queue = ImageBuildQueue::new(
+        Arc::clone(&docker),
+        Repository::new(Arc::clone(&database)),
+        max_concurrent_builds,
+    );
+
+    images_routes(Arc::clone(&database), docker, build_queue)
+        .or(environments_routes(
+            Arc::clone(&database),
+            execution_queue.clone(),
+        ))
         .or(scenarios_routes(Arc::clone(&database)))
-        .or(executions_routes(Arc::clone(&database)))
+        .or(suites_routes(Arc::clone(&database)))
+        .or(executions_routes(Arc::clone(&database), execution_queue))
         .or(simulators_routes(database))
 }
 
 pub async fn rejection_handler(
     rejection: warp::Rejection,
 ) -> Result<impl warp::reply::Reply, Infallible> {
-    let (message, status) = match rejection.find::<ErrorRejection>() {
-        Some(error) => (error.message(), error.status()),
-        None => (
-            "Unknown error",
-            warp::hyper::StatusCode::INTERNAL_SERVER_ERROR,
-        ),
+    let (code, message, details, status) = if let Some(error) = rejection.find::<ApiError>() {
+        (
+            error.code(),
+            error.to_string(),
+            error.details().to_vec(),
+            error.status(),
+        )
+    } else if rejection.is_not_found() {
+        (
+            "NOT_FOUND",
+            String::from("Not found"),
+            Vec::new(),
+            StatusCode::NOT_FOUND,
+        )
+    } else if let Some(error) = rejection.find::<BodyDeserializeError>() {
+        (
+            "VALIDATION_FAILED",
+            String::from("Invalid request body"),
+            vec![error.to_string()],
+            StatusCode::BAD_REQUEST,
+        )
+    } else if let Some(error) = rejection.find::<reject::InvalidQuery>() {
+        (
+            "VALIDATION_FAILED",
+            String::from("Invalid query string"),
+            vec![error.to_string()],
+            StatusCode::BAD_REQUEST,
+        )
+    } else if let Some(error) = rejection.find::<reject::MissingHeader>() {
+        (
+            "VALIDATION_FAILED",
+            error.to_string(),
+            Vec::new(),
+            StatusCode::BAD_REQUEST,
+        )
+    } else if let Some(error) = rejection.find::<reject::InvalidHeader>() {
+        (
+            "VALIDATION_FAILED",
+            error.to_string(),
+            Vec::new(),
+            StatusCode::BAD_REQUEST,
+        )
+    } else if rejection.find::<reject::PayloadTooLarge>().is_some() {
+        (
+            "PAYLOAD_TOO_LARGE",
+            String::from("Payload too large"),
+            Vec::new(),
+            StatusCode::PAYLOAD_TOO_LARGE,
+        )
+    } else if rejection.find::<reject::UnsupportedMediaType>().is_some() {
+        (
+            "UNSUPPORTED_MEDIA_TYPE",
+            String::from("Unsupported media type"),
+            Vec::new(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        )
+    } else if rejection.find::<reject::MethodNotAllowed>().is_some() {
+        (
+            "METHOD_NOT_ALLOWED",
+            String::from("Method not allowed"),
+            Vec::new(),
+            StatusCode::METHOD_NOT_ALLOWED,
+        )
+    } else {
+        (
+            "INTERNAL_ERROR",
+            String::from("Unknown error"),
+            Vec::new(),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )
     };
 
     Ok(warp::reply::with_status(
-        warp::reply::json(&json!({ "message": message })),
+        warp::reply::json(&json!({ "code": code, "message": message, "details": details })),
         status,
     ))
 }
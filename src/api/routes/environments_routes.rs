@@ -1,17 +1,16 @@
 use std::convert::Infallible;
 use std::sync::Arc;
 
-use bollard::Docker;
 use mongodb::Database;
-use tokio::sync::Mutex;
 use warp::Filter;
 
 use crate::api::handlers::environments_handlers;
 use crate::data::Repository;
+use crate::domain::ExecutionQueue;
 
 pub fn environments_routes(
     database: Arc<Database>,
-    docker: Arc<Docker>,
+    execution_queue: ExecutionQueue,
 ) -> impl Filter<Extract=(impl warp::Reply, ), Error=warp::Rejection> + Clone {
     let common = warp::path("environments").and(with_repository(database));
 
@@ -52,18 +51,26 @@ pub fn environments_routes(
         .and(warp::path::end())
         .and_then(environments_handlers::find_simulator_by_id);
 
-    let execution_mutex = Arc::new(Mutex::new(()));
-
     let run_scenario_in_environment = common
         .clone()
         .and(warp::path::param())
         .and(warp::path("scenarios"))
         .and(warp::path::param())
-        .and(with_docker(docker))
         .and(warp::ws())
-        .and(with_mutex(execution_mutex))
+        .and(with_execution_queue(execution_queue.clone()))
         .and_then(environments_handlers::run_scenario_in_environment);
 
+    let queue_scenario_in_environment = common
+        .clone()
+        .and(warp::post())
+        .and(warp::path::param())
+        .and(warp::path("scenarios"))
+        .and(warp::path::param())
+        .and(warp::path("executions"))
+        .and(warp::path::end())
+        .and(with_execution_queue(execution_queue.clone()))
+        .and_then(environments_handlers::queue_scenario_in_environment);
+
     let executions_for_scenario_in_environment = common
         .clone()
         .and(warp::get())
@@ -73,6 +80,27 @@ pub fn environments_routes(
         .and(warp::path("executions"))
         .and_then(environments_handlers::executions_for_scenario_in_environment);
 
+    let run_suite_in_environment = common
+        .clone()
+        .and(warp::post())
+        .and(warp::path::param())
+        .and(warp::path("suites"))
+        .and(warp::path::param())
+        .and(warp::path("executions"))
+        .and(warp::path::end())
+        .and(with_execution_queue(execution_queue))
+        .and_then(environments_handlers::run_suite_in_environment);
+
+    let executions_for_suite_in_environment = common
+        .clone()
+        .and(warp::get())
+        .and(warp::path::param())
+        .and(warp::path("suites"))
+        .and(warp::path::param())
+        .and(warp::path("executions"))
+        .and(warp::path::end())
+        .and_then(environments_handlers::executions_for_suite_in_environment);
+
     let add_simulator_for_environment = common
         .and(warp::post())
         .and(warp::path::param())
@@ -86,14 +114,17 @@ pub fn environments_routes(
         .or(simulators_for_environment)
         .or(find_simulator_by_id)
         .or(run_scenario_in_environment)
+        .or(queue_scenario_in_environment)
         .or(executions_for_scenario_in_environment)
+        .or(run_suite_in_environment)
+        .or(executions_for_suite_in_environment)
         .or(add_simulator_for_environment)
 }
 
-fn with_mutex(
-    mutex: Arc<Mutex<()>>
-) -> impl Filter<Extract=(Arc<Mutex<()>>, ), Error=Infallible> + Clone {
-    warp::any().map(move || Arc::clone(&mutex))
+fn with_execution_queue(
+    execution_queue: ExecutionQueue,
+) -> impl Filter<Extract=(ExecutionQueue, ), Error=Infallible> + Clone {
+    warp::any().map(move || execution_queue.clone())
 }
 
 fn with_repository(
@@ -101,9 +132,3 @@ fn with_repository(
 ) -> impl Filter<Extract=(Repository, ), Error=Infallible> + Clone {
     warp::any().map(move || Repository::new(database.clone()))
 }
-
-fn with_docker(
-    docker: Arc<Docker>,
-) -> impl Filter<Extract=(Arc<Docker>, ), Error=Infallible> + Clone {
-    warp::any().map(move || docker.clone())
-}
@@ -6,17 +6,68 @@ use warp::Filter;
 
 use crate::api::handlers::executions_handler;
 use crate::data::Repository;
+use crate::domain::ExecutionQueue;
 
 pub fn executions_routes(
     database: Arc<Database>,
+    execution_queue: ExecutionQueue,
 ) -> impl Filter<Extract=(impl warp::reply::Reply, ), Error=warp::Rejection> + Clone {
     let common = warp::path("executions").and(with_repository(database));
 
-    common
+    let list = common
+        .clone()
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(warp::query())
+        .and_then(executions_handler::list);
+
+    let analytics = common
+        .clone()
+        .and(warp::get())
+        .and(warp::path("analytics"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and_then(executions_handler::analytics);
+
+    let find_by_id = common
+        .clone()
+        .and(warp::get())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and_then(executions_handler::find_by_id);
+
+    let report = common
+        .clone()
         .and(warp::get())
         .and(warp::path::param())
+        .and(warp::path("report"))
         .and(warp::path::end())
-        .and_then(executions_handler::find_by_id)
+        .and(warp::query())
+        .and_then(executions_handler::report);
+
+    let attach = common
+        .clone()
+        .and(warp::path::param())
+        .and(warp::path("events"))
+        .and(warp::path::end())
+        .and(warp::ws())
+        .and(with_execution_queue(execution_queue.clone()))
+        .and_then(executions_handler::attach);
+
+    let cancel = common
+        .and(warp::delete())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(with_execution_queue(execution_queue))
+        .and_then(executions_handler::cancel);
+
+    list.or(analytics).or(find_by_id).or(report).or(attach).or(cancel)
+}
+
+fn with_execution_queue(
+    execution_queue: ExecutionQueue,
+) -> impl Filter<Extract=(ExecutionQueue, ), Error=Infallible> + Clone {
+    warp::any().map(move || execution_queue.clone())
 }
 
 fn with_repository(
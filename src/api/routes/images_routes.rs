@@ -7,10 +7,12 @@ use warp::Filter;
 
 use crate::api::handlers::images_handlers;
 use crate::data::Repository;
+use crate::domain::ImageBuildQueue;
 
 pub fn images_routes(
     database: Arc<Database>,
     docker: Arc<Docker>,
+    build_queue: ImageBuildQueue,
 ) -> impl Filter<Extract=(impl warp::Reply, ), Error=warp::Rejection> + Clone {
     let common = warp::path("images").and(with_repository(database));
 
@@ -24,7 +26,8 @@ pub fn images_routes(
         .clone()
         .and(warp::post())
         .and(warp::path::end())
-        .and(with_docker(docker))
+        .and(with_build_queue(build_queue))
+        .and(warp::header::optional::<String>("accept"))
         .and(warp::filters::multipart::form())
         .and_then(images_handlers::create);
 
@@ -32,15 +35,57 @@ pub fn images_routes(
         .clone()
         .and(warp::get())
         .and(warp::path::param())
+        .and(warp::path::end())
         .and_then(images_handlers::find_by_id);
 
+    let build_log = common
+        .clone()
+        .and(warp::get())
+        .and(warp::path::param())
+        .and(warp::path("log"))
+        .and(warp::path::end())
+        .and_then(images_handlers::build_log);
+
+    let update = common
+        .clone()
+        .and(warp::put())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and_then(images_handlers::update);
+
+    let remove = common
+        .clone()
+        .and(warp::delete())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(with_docker(docker))
+        .and_then(images_handlers::remove);
+
     let find_by_name = common
+        .clone()
         .and(warp::get())
         .and(warp::path("name"))
         .and(warp::path::param())
+        .and(warp::path::end())
         .and_then(images_handlers::find_by_name);
 
-    list.or(create).or(find_by_id).or(find_by_name)
+    let diff_versions = common
+        .and(warp::get())
+        .and(warp::path("name"))
+        .and(warp::path::param())
+        .and(warp::path("diff"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and_then(images_handlers::diff_versions);
+
+    list.or(create)
+        .or(find_by_id)
+        .or(build_log)
+        .or(update)
+        .or(remove)
+        .or(find_by_name)
+        .or(diff_versions)
 }
 
 fn with_repository(
@@ -54,3 +99,9 @@ fn with_docker(
 ) -> impl Filter<Extract=(Arc<Docker>, ), Error=Infallible> + Clone {
     warp::any().map(move || docker.clone())
 }
+
+fn with_build_queue(
+    build_queue: ImageBuildQueue,
+) -> impl Filter<Extract=(ImageBuildQueue, ), Error=Infallible> + Clone {
+    warp::any().map(move || build_queue.clone())
+}
@@ -3,9 +3,11 @@ pub use executions_routes::executions_routes;
 pub use images_routes::images_routes;
 pub use scenarios_routes::scenarios_routes;
 pub use simulators_routes::simulators_routes;
+pub use suites_routes::suites_routes;
 
 mod environments_routes;
 mod executions_routes;
 mod images_routes;
 mod scenarios_routes;
 mod simulators_routes;
+mod suites_routes;
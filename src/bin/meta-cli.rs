@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use chrono::Local;
+use clap::{Args, Parser, Subcommand};
+use mongodb::bson::doc;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use meta_backend::config::Config;
+use meta_backend::data::{
+    Environment, Execution, ExecutionDTO, ExecutionStatus, Repository, Runner, Scenario,
+    ScenarioPlayingEvent, Suite, SuiteExecution, SuiteExecutionDTO, Timing,
+};
+use meta_backend::domain::{DockerScenarioExecutor, DockerSuiteExecutor};
+use meta_backend::loaders;
+
+/// Exit code of runs where a scenario failed.
+const FAILED: u8 = 1;
+/// Exit code of runs that could not complete, because of their setup or of the infrastructure.
+const ERRORED: u8 = 2;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "meta-cli",
+    about = "Runs scenarios and suites without the web interface"
+)]
+struct Cli {
+    #[command(subcommand)]
+    target: Target,
+}
+
+#[derive(Debug, Subcommand)]
+enum Target {
+    /// Runs the scenario with this name
+    Scenario {
+        name: String,
+        #[command(flatten)]
+        options: RunOptions,
+    },
+    /// Runs every scenario of the suite with this name
+    Suite {
+        name: String,
+        #[command(flatten)]
+        options: RunOptions,
+    },
+}
+
+#[derive(Debug, Args)]
+struct RunOptions {
+    /// Name of the environment to run in
+    #[arg(long, short)]
+    environment: String,
+    /// Writes a JSON report of the run to this file
+    #[arg(long)]
+    report: Option<PathBuf>,
+    /// Config file, `meta.toml` being read when it exists
+    #[arg(long, env = "META_CONFIG")]
+    config: Option<PathBuf>,
+    #[arg(long, env = "META_DATABASE_URI")]
+    database_uri: Option<String>,
+    #[arg(long, env = "META_DATABASE_NAME")]
+    database_name: Option<String>,
+    #[arg(long, env = "META_DOCKER_HOST")]
+    docker_host: Option<String>,
+}
+
+impl RunOptions {
+    fn config(&self) -> Result<Config, Box<dyn Error>> {
+        let mut config = Config::read(self.config.as_deref())?;
+
+        if let Some(database_uri) = &self.database_uri {
+            config.database.uri = database_uri.clone();
+        }
+
+        if let Some(database_name) = &self.database_name {
+            config.database.name = database_name.clone();
+        }
+
+        if let Some(docker_host) = &self.docker_host {
+            config.docker.host = Some(docker_host.clone());
+        }
+
+        config.validate(None)?;
+
+        Ok(config)
+    }
+}
+
+/// Machine readable outcome of a run, with every execution it made.
+#[derive(Debug, Serialize)]
+struct Report {
+    name: String,
+    environment: String,
+    status: ExecutionStatus,
+    #[serde(rename = "suiteExecution")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suite_execution: Option<SuiteExecutionDTO>,
+    executions: Vec<ExecutionDTO>,
+}
+
+struct Context {
+    config: Config,
+    repository: Repository,
+    docker: Arc<bollard::Docker>,
+    environment: Environment,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let (name, options) = match &cli.target {
+        Target::Scenario { name, options } | Target::Suite { name, options } => (name, options),
+    };
+
+    let result = match connect(options).await {
+        Ok(context) => match &cli.target {
+            Target::Scenario { .. } => run_scenario(&context, name).await,
+            Target::Suite { .. } => run_suite(&context, name).await,
+        },
+        Err(error) => Err(error),
+    };
+
+    let report = match result {
+        Ok(report) => report,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::from(ERRORED);
+        }
+    };
+
+    println!("{}: {:?}", report.name, report.status);
+
+    if let Some(path) = &options.report {
+        let written = serde_json::to_vec_pretty(&report)
+            .map_err(|error| error.to_string())
+            .and_then(|content| std::fs::write(path, content).map_err(|error| error.to_string()));
+
+        if let Err(error) = written {
+            eprintln!("Could not write report to {}: {}", path.display(), error);
+            return ExitCode::from(ERRORED);
+        }
+    }
+
+    match report.status {
+        ExecutionStatus::Passed => ExitCode::SUCCESS,
+        ExecutionStatus::Failed => ExitCode::from(FAILED),
+        _ => ExitCode::from(ERRORED),
+    }
+}
+
+async fn connect(options: &RunOptions) -> Result<Context, Box<dyn Error>> {
+    let config = options.config()?;
+
+    let docker = loaders::initialize_docker(&config.docker).await?;
+    let database = loaders::initialize_database(&config.database).await?;
+    let repository = Repository::new(Arc::new(database));
+
+    let environment = repository
+        .find_one::<Environment>(doc! { "name": &options.environment })
+        .await?
+        .ok_or_else(|| format!("Environment {} not found", options.environment))?;
+
+    Ok(Context {
+        config,
+        repository,
+        docker: Arc::new(docker),
+        environment,
+    })
+}
+
+async fn run_scenario(context: &Context, name: &str) -> Result<Report, Box<dyn Error>> {
+    let scenario = context
+        .repository
+        .find_one::<Scenario>(doc! { "name": name })
+        .await?
+        .ok_or_else(|| format!("Scenario {} not found", name))?;
+
+    let execution = context
+        .repository
+        .create(
+            Execution::new(
+                *scenario.id().unwrap(),
+                *context.environment.id().unwrap(),
+                scenario.steps().clone(),
+                Local::now(),
+            )
+            .run_by(Runner::Cli),
+        )
+        .await?;
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    let printer = tokio::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            println!("{}", describe(&event));
+        }
+    });
+
+    let execution = DockerScenarioExecutor::new(
+        context.docker.clone(),
+        context.repository.clone(),
+        context.config.execution.settings(),
+    )
+    .run_scenario_in_environment(
+        &context.environment,
+        &scenario,
+        execution,
+        Some(tx),
+        cancel_on_interrupt(),
+    )
+    .await?;
+
+    printer.await.ok();
+
+    Ok(Report {
+        name: name.to_owned(),
+        environment: context.environment.name().to_owned(),
+        status: execution.status(),
+        suite_execution: None,
+        executions: vec![ExecutionDTO::from(execution)],
+    })
+}
+
+async fn run_suite(context: &Context, name: &str) -> Result<Report, Box<dyn Error>> {
+    let suite = context
+        .repository
+        .find_one::<Suite>(doc! { "name": name })
+        .await?
+        .ok_or_else(|| format!("Suite {} not found", name))?;
+
+    let scenario_names = context
+        .repository
+        .find::<Scenario>(doc! { "_id": { "$in": suite.scenario_ids() } })
+        .await?
+        .into_iter()
+        .map(|scenario| (*scenario.id().unwrap(), scenario.name().to_owned()))
+        .collect::<HashMap<_, _>>();
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    let printer = tokio::spawn(async move {
+        while let Some((scenario_id, event)) = rx.recv().await {
+            let scenario_name = scenario_names
+                .get(&scenario_id)
+                .map(String::as_str)
+                .unwrap_or("?");
+
+            println!("[{}] {}", scenario_name, describe(&event));
+        }
+    });
+
+    let suite_execution = context
+        .repository
+        .create(
+            SuiteExecution::new(
+                *suite.id().unwrap(),
+                *context.environment.id().unwrap(),
+                Local::now(),
+            )
+            .run_by(Runner::Cli),
+        )
+        .await?;
+
+    let suite_execution = DockerSuiteExecutor::new(
+        context.docker.clone(),
+        context.repository.clone(),
+        context.config.execution.settings(),
+    )
+    .run_suite_in_environment(
+        &context.environment,
+        &suite,
+        suite_execution,
+        Some(tx),
+        cancel_on_interrupt(),
+    )
+    .await?;
+
+    printer.await.ok();
+
+    let mut executions = Vec::new();
+
+    for result in suite_execution.results() {
+        if let Some(error) = result.error() {
+            println!("Scenario {} could not run: {}", result.scenario_id(), error);
+        }
+
+        if let Some(execution_id) = result.execution_id() {
+            if let Some(execution) = context
+                .repository
+                .find_by_id::<Execution>(execution_id)
+                .await?
+            {
+                executions.push(ExecutionDTO::from(execution));
+            }
+        }
+    }
+
+    println!(
+        "{} passed, {} failed, {} errored, {} cancelled",
+        suite_execution.passed(),
+        suite_execution.failed(),
+        suite_execution.errored(),
+        suite_execution.cancelled()
+    );
+
+    Ok(Report {
+        name: name.to_owned(),
+        environment: context.environment.name().to_owned(),
+        status: suite_execution.status(),
+        suite_execution: Some(SuiteExecutionDTO::from(suite_execution)),
+        executions,
+    })
+}
+
+/// Cancels the run on Ctrl+C, so that its simulators get removed and the rest of a suite is skipped.
+fn cancel_on_interrupt() -> CancellationToken {
+    let cancellation = CancellationToken::new();
+    let token = cancellation.clone();
+
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            token.cancel();
+        }
+    });
+
+    cancellation
+}
+
+fn describe(event: &ScenarioPlayingEvent) -> String {
+    match event {
+        ScenarioPlayingEvent::ScenarioStarting => String::from("Scenario starting"),
+        ScenarioPlayingEvent::StepPassed {
+            step,
+            message,
+            timing,
+        } => {
+            format!("Step {} passed{}: {}", step, took(timing), message)
+        }
+        ScenarioPlayingEvent::StepFailed {
+            step,
+            message,
+            status,
+            failures,
+            timing,
+        } => {
+            let mut description = format!(
+                "Step {} failed ({}){}: {}",
+                step,
+                status,
+                took(timing),
+                message
+            );
+
+            for failure in failures {
+                description.push_str(&format!(
+                    "\n  {}: expected {}, got {}",
+                    failure.message, failure.expected, failure.actual
+                ));
+            }
+
+            description
+        }
+        ScenarioPlayingEvent::StepTimedOut {
+            step, timeout_ms, ..
+        } => {
+            format!("Step {} timed out after {}ms", step, timeout_ms)
+        }
+        ScenarioPlayingEvent::VariableCaptured { step, name, value } => {
+            format!("Step {} captured {} = {}", step, name, value)
+        }
+        ScenarioPlayingEvent::LogReceived { log_message } => {
+            format!("{} | {}", log_message.simulator_name, log_message.message)
+        }
+        ScenarioPlayingEvent::ScenarioCancelled => String::from("Scenario cancelled"),
+        // Setup failures are rare enough to be shown as they are
+        other => serde_json::to_string(other).unwrap_or_default(),
+    }
+}
+
+fn took(timing: &Option<Timing>) -> String {
+    timing
+        .map(|timing| format!(" in {}ms", timing.duration_ms))
+        .unwrap_or_default()
+}
@@ -0,0 +1,349 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::{Parser, Subcommand};
+use serde::Deserialize;
+
+use crate::domain::ExecutionSettings;
+
+const DEFAULT_CONFIG_FILE: &str = "meta.toml";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("Could not read config file {path}: {message}")]
+    File { path: PathBuf, message: String },
+    #[error("Invalid configuration:\n  {}", .0.join("\n  "))]
+    Invalid(Vec<String>),
+}
+
+/// Settings of the server. Each one comes from, in order of precedence, a command line flag, an
+/// environment variable, the config file, or its default value.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub docker: DockerConfig,
+    pub execution: ExecutionConfig,
+    pub seed: SeedConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+pub struct ServerConfig {
+    pub address: SocketAddr,
+    pub cors_origins: Vec<String>,
+    pub log_filter: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub uri: String,
+    pub name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+pub struct DockerConfig {
+    /// Uses the default Docker daemon when missing. Simulators are called on localhost, so the
+    /// daemon must run on this machine.
+    pub host: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+pub struct ExecutionConfig {
+    pub command_timeout_ms: u64,
+    pub readiness_timeout_ms: u64,
+    pub max_concurrent_executions: usize,
+    pub max_concurrent_builds: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+pub struct SeedConfig {
+    pub enabled: bool,
+    pub greeting_sim_archive: PathBuf,
+    pub manager_archive: PathBuf,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            address: SocketAddr::from(([127, 0, 0, 1], 4000)),
+            cors_origins: vec![String::from("http://localhost:1234")],
+            log_filter: String::from("trace,hyper=warn,tokio_util=warn,warp=info"),
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            uri: String::from("mongodb://localhost:27017"),
+            name: String::from("meta"),
+        }
+    }
+}
+
+impl Default for ExecutionConfig {
+    fn default() -> Self {
+        let execution_settings = ExecutionSettings::default();
+
+        Self {
+            command_timeout_ms: execution_settings.command_timeout.as_millis() as u64,
+            readiness_timeout_ms: execution_settings.readiness_timeout.as_millis() as u64,
+            max_concurrent_executions: execution_settings.max_concurrent_executions,
+            max_concurrent_builds: 1,
+        }
+    }
+}
+
+impl Default for SeedConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            greeting_sim_archive: PathBuf::from("greeting-sim.tar.gz"),
+            manager_archive: PathBuf::from("manager.tar.gz"),
+        }
+    }
+}
+
+impl ExecutionConfig {
+    pub fn settings(&self) -> ExecutionSettings {
+        ExecutionSettings {
+            command_timeout: Duration::from_millis(self.command_timeout_ms),
+            readiness_timeout: Duration::from_millis(self.readiness_timeout_ms),
+            max_concurrent_executions: self.max_concurrent_executions,
+        }
+    }
+}
+
+/// Whether the Docker daemon at this URL runs on this machine.
+fn is_local(host: &str) -> bool {
+    let Some(host) = reqwest::Url::parse(host)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_owned))
+    else {
+        return false;
+    };
+
+    host == "localhost"
+        || host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+/// What to do instead of serving the API.
+#[derive(Debug, Clone, Copy, Subcommand)]
+pub enum ServerCommand {
+    /// Removes every document and every Meta image from Docker, then adds the seed data again
+    Reset,
+}
+
+#[derive(Debug, Parser)]
+#[command(about = "Runs the Meta backend")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<ServerCommand>,
+    /// Config file, `meta.toml` being read when it exists
+    #[arg(long, env = "META_CONFIG")]
+    config: Option<PathBuf>,
+    #[arg(long, env = "META_ADDRESS")]
+    address: Option<SocketAddr>,
+    /// Origins allowed to call the API, separated by commas
+    #[arg(long, env = "META_CORS_ORIGINS", value_delimiter = ',')]
+    cors_origins: Option<Vec<String>>,
+    #[arg(long, env = "META_LOG_FILTER")]
+    log_filter: Option<String>,
+    #[arg(long, env = "META_DATABASE_URI")]
+    database_uri: Option<String>,
+    #[arg(long, env = "META_DATABASE_NAME")]
+    database_name: Option<String>,
+    /// Docker daemon running on this machine, such as `unix:///var/run/docker.sock` or
+    /// `tcp://localhost:2375`
+    #[arg(long, env = "META_DOCKER_HOST")]
+    docker_host: Option<String>,
+    #[arg(long, env = "META_COMMAND_TIMEOUT_MS")]
+    command_timeout_ms: Option<u64>,
+    #[arg(long, env = "META_READINESS_TIMEOUT_MS")]
+    readiness_timeout_ms: Option<u64>,
+    #[arg(long, env = "META_MAX_CONCURRENT_EXECUTIONS")]
+    max_concurrent_executions: Option<usize>,
+    #[arg(long, env = "META_MAX_CONCURRENT_BUILDS")]
+    max_concurrent_builds: Option<usize>,
+    /// Adds the seed data at startup when the database is empty
+    #[arg(long, env = "META_SEED", num_args = 0..=1, default_missing_value = "true")]
+    seed: Option<bool>,
+    #[arg(long, env = "META_SEED_GREETING_SIM_ARCHIVE")]
+    seed_greeting_sim_archive: Option<PathBuf>,
+    #[arg(long, env = "META_SEED_MANAGER_ARCHIVE")]
+    seed_manager_archive: Option<PathBuf>,
+}
+
+impl Config {
+    /// Reads the configuration from the command line, the environment and the config file, along
+    /// with the command to run instead of serving the API.
+    pub fn load() -> Result<(Self, Option<ServerCommand>), ConfigError> {
+        let cli = Cli::parse();
+        let command = cli.command;
+
+        let mut config = Self::read(cli.config.as_deref())?;
+
+        config.apply(cli);
+        config.validate(command)?;
+
+        Ok((config, command))
+    }
+
+    /// Reads the given config file, or `meta.toml` when it exists, falling back to the defaults.
+    pub fn read(path: Option<&Path>) -> Result<Self, ConfigError> {
+        match path {
+            Some(path) => Self::from_file(path),
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_FILE))
+            }
+            None => Ok(Self::default()),
+        }
+    }
+
+    fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let file_error = |message: String| ConfigError::File {
+            path: path.to_owned(),
+            message,
+        };
+
+        let content =
+            std::fs::read_to_string(path).map_err(|error| file_error(error.to_string()))?;
+
+        toml::from_str(&content).map_err(|error| file_error(error.to_string()))
+    }
+
+    fn apply(&mut self, cli: Cli) {
+        fn set<T>(value: &mut T, override_value: Option<T>) {
+            if let Some(override_value) = override_value {
+                *value = override_value;
+            }
+        }
+
+        set(&mut self.server.address, cli.address);
+        set(&mut self.server.cors_origins, cli.cors_origins);
+        set(&mut self.server.log_filter, cli.log_filter);
+        set(&mut self.database.uri, cli.database_uri);
+        set(&mut self.database.name, cli.database_name);
+        set(&mut self.docker.host, cli.docker_host.map(Some));
+        set(
+            &mut self.execution.command_timeout_ms,
+            cli.command_timeout_ms,
+        );
+        set(
+            &mut self.execution.readiness_timeout_ms,
+            cli.readiness_timeout_ms,
+        );
+        set(
+            &mut self.execution.max_concurrent_executions,
+            cli.max_concurrent_executions,
+        );
+        set(
+            &mut self.execution.max_concurrent_builds,
+            cli.max_concurrent_builds,
+        );
+        set(&mut self.seed.enabled, cli.seed);
+        set(
+            &mut self.seed.greeting_sim_archive,
+            cli.seed_greeting_sim_archive,
+        );
+        set(&mut self.seed.manager_archive, cli.seed_manager_archive);
+    }
+
+    pub fn validate(&self, command: Option<ServerCommand>) -> Result<(), ConfigError> {
+        let mut errors = Vec::new();
+
+        if self.server.cors_origins.iter().any(|origin| {
+            warp::http::HeaderValue::from_str(origin).is_err() || !origin.contains("://")
+        }) {
+            errors.push(String::from(
+                "server.cors_origins must only contain origins such as http://localhost:1234",
+            ));
+        }
+
+        if let Err(error) = tracing_subscriber::EnvFilter::try_new(&self.server.log_filter) {
+            errors.push(format!("server.log_filter is invalid: {}", error));
+        }
+
+        if !self.database.uri.starts_with("mongodb://")
+            && !self.database.uri.starts_with("mongodb+srv://")
+        {
+            errors.push(String::from(
+                "database.uri must be a MongoDB connection string",
+            ));
+        }
+
+        if self.database.name.trim().is_empty() {
+            errors.push(String::from("database.name must not be empty"));
+        }
+
+        if let Some(host) = &self.docker.host {
+            if !["unix://", "tcp://", "http://"]
+                .iter()
+                .any(|scheme| host.starts_with(scheme))
+            {
+                errors.push(String::from(
+                    "docker.host must start with unix://, tcp:// or http://",
+                ));
+            } else if !host.starts_with("unix://") && !is_local(host) {
+                // Simulators publish their port on the Docker host, and are called on localhost
+                errors.push(String::from(
+                    "docker.host must run on this machine, such as tcp://localhost:2375",
+                ));
+            }
+        }
+
+        for (name, value) in [
+            (
+                "execution.command_timeout_ms",
+                self.execution.command_timeout_ms,
+            ),
+            (
+                "execution.readiness_timeout_ms",
+                self.execution.readiness_timeout_ms,
+            ),
+            (
+                "execution.max_concurrent_executions",
+                self.execution.max_concurrent_executions as u64,
+            ),
+            (
+                "execution.max_concurrent_builds",
+                self.execution.max_concurrent_builds as u64,
+            ),
+        ] {
+            if value == 0 {
+                errors.push(format!("{} must be a positive number", name));
+            }
+        }
+
+        if self.seed.enabled || matches!(command, Some(ServerCommand::Reset)) {
+            for archive in [&self.seed.greeting_sim_archive, &self.seed.manager_archive] {
+                if !archive.is_file() {
+                    errors.push(format!("Seed archive {} does not exist", archive.display()));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+}
@@ -2,6 +2,8 @@
 pub enum DataError {
     #[error("{0}")]
     InitializationError(#[from] mongodb::error::Error),
+    #[error("{0}")]
+    Deserialization(#[from] mongodb::bson::de::Error),
     #[error("Not found")]
     NotFound,
 }
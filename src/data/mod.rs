@@ -1,8 +1,16 @@
 pub use error::DataError;
-pub use models::{Command, Environment, Execution, Image, Scenario, Simulator, Step, Tag};
+pub use models::{deserialize_date, to_bson_date, to_stored_bson};
+pub use models::{AssertionFailure, Capture, CommandsDiff, Expectation};
+pub use models::{
+    Command, Environment, Execution, ExecutionStatus, Image, Scenario, Simulator, Step, Tag,
+};
 pub use models::{EnvironmentDTO, ExecutionDTO, ImageDTO, ScenarioDTO, SimulatorDTO, StepDTO};
-pub use models::{LogMessage, ScenarioPlayingEvent};
-pub use repository::Repository;
+pub use models::{ExecutionTimings, Timing};
+pub use models::{ImageBuildEvent, ImageStatus, ImageVersion};
+pub use models::{LogMessage, Runner, ScenarioPlayingCommand, ScenarioPlayingEvent};
+pub use models::{Suite, SuiteExecution, SuiteScenarioResult};
+pub use models::{SuiteDTO, SuiteExecutionDTO};
+pub use repository::{QueryOptions, Repository};
 
 pub(crate) mod error;
 mod models;
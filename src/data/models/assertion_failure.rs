@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::Expectation;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AssertionFailure {
+    pub expectation: Expectation,
+    pub expected: Value,
+    pub actual: Value,
+    pub message: String,
+}
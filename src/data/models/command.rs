@@ -1,19 +1,70 @@
-use mongodb::bson::{bson, doc};
+use mongodb::bson::{bson, to_bson};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Command {
     pub name: String,
     pub description: String,
     pub path: String,
+    #[serde(rename = "timeoutMs")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    #[serde(rename = "argumentsSchema")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments_schema: Option<Value>,
+    #[serde(rename = "responseSchema")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_schema: Option<Value>,
 }
 
-impl From<Command> for mongodb::bson::Bson {
-    fn from(command: Command) -> Self {
-        bson! ({
+/// Schemas and timeouts are written by users, and may hold numbers BSON cannot represent.
+impl TryFrom<Command> for mongodb::bson::Bson {
+    type Error = mongodb::bson::ser::Error;
+
+    fn try_from(command: Command) -> Result<Self, Self::Error> {
+        Ok(bson! ({
             "name": command.name,
             "description": command.description,
             "path": command.path,
-        })
+            "timeoutMs": to_bson(&command.timeout_ms)?,
+            "argumentsSchema": to_bson(&command.arguments_schema)?,
+            "responseSchema": to_bson(&command.response_schema)?,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::Bson;
+
+    use super::*;
+
+    fn command(timeout_ms: Option<u64>) -> Command {
+        Command {
+            name: String::from("Hello"),
+            description: String::new(),
+            path: String::from("hello"),
+            timeout_ms,
+            arguments_schema: None,
+            response_schema: None,
+        }
+    }
+
+    #[test]
+    fn saves_timeouts_as_integers() {
+        let Bson::Document(document) = Bson::try_from(command(Some(500))).unwrap() else {
+            panic!("Commands are saved as documents");
+        };
+
+        assert_eq!(document.get("timeoutMs"), Some(&Bson::Int64(500)));
+    }
+
+    #[test]
+    fn rejects_timeouts_beyond_the_range_of_bson() {
+        assert!(Bson::try_from(command(Some(u64::MAX))).is_err());
     }
 }
@@ -0,0 +1,138 @@
+use serde::Serialize;
+
+use super::Command;
+
+#[derive(Debug, Serialize)]
+pub struct ChangedCommand {
+    path: String,
+    from: Command,
+    to: Command,
+}
+
+/// The commands added, removed or changed between two versions of an image, matched by path.
+#[derive(Debug, Serialize)]
+pub struct CommandsDiff {
+    added: Vec<Command>,
+    removed: Vec<Command>,
+    changed: Vec<ChangedCommand>,
+}
+
+impl CommandsDiff {
+    pub fn between(from: &[Command], to: &[Command]) -> Self {
+        let find = |commands: &[Command], path: &str| {
+            commands
+                .iter()
+                .find(|command| command.path == path)
+                .cloned()
+        };
+
+        let added = to
+            .iter()
+            .filter(|command| find(from, &command.path).is_none())
+            .cloned()
+            .collect();
+
+        let removed = from
+            .iter()
+            .filter(|command| find(to, &command.path).is_none())
+            .cloned()
+            .collect();
+
+        let changed = from
+            .iter()
+            .filter_map(|from_command| {
+                let to_command = find(to, &from_command.path)?;
+
+                let unchanged = serde_json::to_value(from_command).ok()
+                    == serde_json::to_value(&to_command).ok();
+
+                (!unchanged).then(|| ChangedCommand {
+                    path: from_command.path.clone(),
+                    from: from_command.clone(),
+                    to: to_command,
+                })
+            })
+            .collect();
+
+        Self {
+            added,
+            removed,
+            changed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn command(path: &str) -> Command {
+        Command {
+            name: path.to_uppercase(),
+            description: format!("Calls {}", path),
+            path: String::from(path),
+            timeout_ms: None,
+            arguments_schema: None,
+            response_schema: None,
+        }
+    }
+
+    fn paths(commands: &[Command]) -> Vec<&str> {
+        commands
+            .iter()
+            .map(|command| command.path.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn finds_added_commands() {
+        let diff = CommandsDiff::between(&[command("hello")], &[command("hello"), command("bye")]);
+
+        assert_eq!(paths(&diff.added), vec!["bye"]);
+        assert!(diff.removed.is_empty());
+        assert!(diff.changed.is_empty());
+    }
+
+    #[test]
+    fn finds_removed_commands() {
+        let diff = CommandsDiff::between(&[command("hello"), command("bye")], &[command("bye")]);
+
+        assert!(diff.added.is_empty());
+        assert_eq!(paths(&diff.removed), vec!["hello"]);
+        assert!(diff.changed.is_empty());
+    }
+
+    #[test]
+    fn finds_changed_commands_by_path() {
+        let changed = Command {
+            timeout_ms: Some(500),
+            arguments_schema: Some(json!({ "type": "object" })),
+            ..command("hello")
+        };
+
+        let diff = CommandsDiff::between(
+            &[command("hello"), command("bye")],
+            &[changed, command("bye")],
+        );
+
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].path, "hello");
+        assert_eq!(diff.changed[0].from.timeout_ms, None);
+        assert_eq!(diff.changed[0].to.timeout_ms, Some(500));
+    }
+
+    #[test]
+    fn identical_commands_make_an_empty_diff() {
+        let commands = [command("hello"), command("bye")];
+
+        let diff = CommandsDiff::between(&commands, &commands);
+
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert!(diff.changed.is_empty());
+    }
+}
@@ -1,11 +1,55 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Local};
 use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, to_bson};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::data::models::scenario_playing_event::ScenarioPlayingEvent;
+use crate::data::models::timing::ExecutionTimings;
+use crate::data::models::{Runner, Step, StepDTO};
 use crate::data::repository::Document;
 
-use super::serializers::serialize_option_object_id;
+use super::serializers::{
+    deserialize_date, deserialize_option_date, serialize_date, serialize_option_date,
+    serialize_option_object_id, to_bson_date, to_stored_bson,
+};
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionStatus {
+    Queued,
+    Starting,
+    Running,
+    Passed,
+    Failed,
+    Cancelled,
+    Errored,
+}
+
+impl ExecutionStatus {
+    /// The statuses of executions that did not end yet.
+    pub const UNFINISHED: [ExecutionStatus; 3] = [
+        ExecutionStatus::Queued,
+        ExecutionStatus::Starting,
+        ExecutionStatus::Running,
+    ];
+
+    fn from_events(events: &[ScenarioPlayingEvent]) -> Self {
+        let mut status = ExecutionStatus::Passed;
+
+        for event in events {
+            match event {
+                ScenarioPlayingEvent::ScenarioCancelled => return ExecutionStatus::Cancelled,
+                ScenarioPlayingEvent::StepFailed { .. }
+                | ScenarioPlayingEvent::StepTimedOut { .. } => status = ExecutionStatus::Failed,
+                _ => {}
+            }
+        }
+
+        status
+    }
+}
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ExecutionDTO {
@@ -16,8 +60,21 @@ pub struct ExecutionDTO {
     #[serde(rename = "environmentId")]
     environment_id: String,
     timestamp: DateTime<Local>,
+    #[serde(rename = "startedAt")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    started_at: Option<DateTime<Local>>,
+    #[serde(rename = "endedAt")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ended_at: Option<DateTime<Local>>,
+    #[serde(rename = "durationMs")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u64>,
+    timings: ExecutionTimings,
     events: Vec<ScenarioPlayingEvent>,
-    successful: bool,
+    variables: HashMap<String, Value>,
+    status: ExecutionStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    steps: Option<Vec<StepDTO>>,
 }
 
 impl From<Execution> for ExecutionDTO {
@@ -27,13 +84,21 @@ impl From<Execution> for ExecutionDTO {
             scenario_id: execution.scenario_id.to_string(),
             environment_id: execution.environment_id.to_string(),
             timestamp: execution.timestamp,
+            started_at: execution.started_at,
+            ended_at: execution.ended_at,
+            duration_ms: execution.duration_ms,
+            timings: execution.timings,
             events: execution.events,
-            successful: execution.successful,
+            variables: execution.variables,
+            status: execution.status,
+            steps: execution
+                .steps
+                .map(|steps| steps.into_iter().map(StepDTO::from).collect()),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Execution {
     #[serde(alias = "_id")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -43,31 +108,174 @@ pub struct Execution {
     scenario_id: ObjectId,
     #[serde(rename = "environmentId")]
     environment_id: ObjectId,
+    #[serde(serialize_with = "serialize_date")]
+    #[serde(deserialize_with = "deserialize_date")]
     timestamp: DateTime<Local>,
+    #[serde(rename = "startedAt")]
+    #[serde(default)]
+    #[serde(serialize_with = "serialize_option_date")]
+    #[serde(deserialize_with = "deserialize_option_date")]
+    started_at: Option<DateTime<Local>>,
+    #[serde(rename = "endedAt")]
+    #[serde(default)]
+    #[serde(serialize_with = "serialize_option_date")]
+    #[serde(deserialize_with = "deserialize_option_date")]
+    ended_at: Option<DateTime<Local>>,
+    #[serde(rename = "durationMs")]
+    #[serde(default)]
+    duration_ms: Option<u64>,
+    #[serde(default)]
+    timings: ExecutionTimings,
+    #[serde(default)]
     events: Vec<ScenarioPlayingEvent>,
-    successful: bool,
+    #[serde(default)]
+    variables: HashMap<String, Value>,
+    status: ExecutionStatus,
+    /// Executions saved before they had a runner were all run by the server.
+    #[serde(default)]
+    runner: Runner,
+    /// The steps of the scenario when the execution was created, which it may no longer have.
+    /// Executions saved before they kept them have none.
+    #[serde(default)]
+    steps: Option<Vec<Step>>,
 }
 
 impl Execution {
+    /// Executions keep the steps the scenario has when they are created, as it may change while
+    /// they are queued or after they ran.
     pub fn new(
         scenario_id: ObjectId,
         environment_id: ObjectId,
+        steps: Vec<Step>,
         timestamp: DateTime<Local>,
-        events: Vec<ScenarioPlayingEvent>,
     ) -> Execution {
-        let successful = events
-            .iter()
-            .all(|event| !matches!(event, ScenarioPlayingEvent::StepFailed { .. }));
-
         Self {
             id: None,
             scenario_id,
             environment_id,
             timestamp,
+            started_at: None,
+            ended_at: None,
+            duration_ms: None,
+            timings: ExecutionTimings::default(),
+            events: Vec::new(),
+            variables: HashMap::new(),
+            status: ExecutionStatus::Queued,
+            runner: Runner::Server,
+            steps: Some(steps),
+        }
+    }
+
+    pub fn run_by(self, runner: Runner) -> Self {
+        Self { runner, ..self }
+    }
+
+    /// Marks the execution as taken out of the queue, its simulators being started.
+    pub fn start(self, started_at: DateTime<Local>) -> Self {
+        Self {
+            started_at: Some(started_at),
+            status: ExecutionStatus::Starting,
+            ..self
+        }
+    }
+
+    /// Records the events of a scenario that ran, deriving its outcome from them.
+    pub fn finish(self, events: Vec<ScenarioPlayingEvent>, ended_at: DateTime<Local>) -> Self {
+        Self {
+            status: ExecutionStatus::from_events(&events),
+            ..self.with_events(events).end(ended_at)
+        }
+    }
+
+    /// Records the events of a scenario that could not be run to the end.
+    pub fn abort(self, events: Vec<ScenarioPlayingEvent>, ended_at: DateTime<Local>) -> Self {
+        Self {
+            status: ExecutionStatus::Errored,
+            ..self.with_events(events).end(ended_at)
+        }
+    }
+
+    /// Records where the time of the execution went, before it is finished or aborted.
+    pub fn timed(self, timings: ExecutionTimings) -> Self {
+        Self { timings, ..self }
+    }
+
+    fn end(self, ended_at: DateTime<Local>) -> Self {
+        let duration_ms = self.started_at.map(|started_at| {
+            (ended_at - started_at)
+                .to_std()
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or_default()
+        });
+
+        Self {
+            ended_at: Some(ended_at),
+            duration_ms,
+            ..self
+        }
+    }
+
+    fn with_events(self, events: Vec<ScenarioPlayingEvent>) -> Self {
+        let variables = events
+            .iter()
+            .filter_map(|event| match event {
+                ScenarioPlayingEvent::VariableCaptured { name, value, .. } => {
+                    Some((name.clone(), value.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        Self {
             events,
-            successful,
+            variables,
+            ..self
         }
     }
+
+    pub fn id(&self) -> Option<&ObjectId> {
+        self.id.as_ref()
+    }
+
+    pub fn scenario_id(&self) -> &ObjectId {
+        &self.scenario_id
+    }
+
+    pub fn environment_id(&self) -> &ObjectId {
+        &self.environment_id
+    }
+
+    pub fn timestamp(&self) -> DateTime<Local> {
+        self.timestamp
+    }
+
+    pub fn started_at(&self) -> Option<DateTime<Local>> {
+        self.started_at
+    }
+
+    pub fn ended_at(&self) -> Option<DateTime<Local>> {
+        self.ended_at
+    }
+
+    pub fn duration_ms(&self) -> Option<u64> {
+        self.duration_ms
+    }
+
+    pub fn timings(&self) -> &ExecutionTimings {
+        &self.timings
+    }
+
+    pub fn events(&self) -> &Vec<ScenarioPlayingEvent> {
+        &self.events
+    }
+
+    pub fn status(&self) -> ExecutionStatus {
+        self.status
+    }
+
+    pub fn steps(&self) -> Option<&Vec<Step>> {
+        self.steps.as_ref()
+    }
 }
 
 impl Document for Execution {
@@ -82,3 +290,22 @@ impl Document for Execution {
         }
     }
 }
+
+impl From<Execution> for mongodb::bson::Document {
+    fn from(execution: Execution) -> Self {
+        doc! {
+            "scenarioId": execution.scenario_id,
+            "environmentId": execution.environment_id,
+            "timestamp": to_bson_date(&execution.timestamp),
+            "startedAt": execution.started_at.as_ref().map(to_bson_date),
+            "endedAt": execution.ended_at.as_ref().map(to_bson_date),
+            "durationMs": execution.duration_ms.map(|duration_ms| duration_ms as i64),
+            "timings": to_stored_bson(&execution.timings).unwrap(),
+            "events": to_stored_bson(&execution.events).unwrap(),
+            "variables": to_bson(&execution.variables).unwrap(),
+            "status": to_bson(&execution.status).unwrap(),
+            "runner": to_bson(&execution.runner).unwrap(),
+            "steps": to_stored_bson(&execution.steps).unwrap(),
+        }
+    }
+}
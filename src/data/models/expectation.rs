@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum Expectation {
+    Equals {
+        path: String,
+        value: Value,
+    },
+    Matches {
+        path: String,
+        pattern: String,
+    },
+    InRange {
+        path: String,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min: Option<f64>,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max: Option<f64>,
+    },
+    Status {
+        status: u16,
+    },
+    Schema {
+        schema: Value,
+    },
+}
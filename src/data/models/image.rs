@@ -1,10 +1,11 @@
+use mongodb::bson::{doc, to_bson, Bson};
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 
 use crate::data::repository::Document;
 
 use super::{serializers::serialize_option_object_id, Tag};
-use super::Command;
+use super::{Command, ImageStatus};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ImageDTO {
@@ -14,6 +15,12 @@ pub struct ImageDTO {
     description: String,
     tag: Tag,
     commands: Vec<Command>,
+    status: ImageStatus,
+    #[serde(rename = "dockerImageId")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    docker_image_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<i64>,
 }
 
 impl From<Image> for ImageDTO {
@@ -23,6 +30,9 @@ impl From<Image> for ImageDTO {
             description: image.description,
             tag: image.tag,
             commands: image.commands,
+            status: image.status,
+            docker_image_id: image.docker_image_id,
+            size: image.size,
         }
     }
 }
@@ -35,7 +45,18 @@ pub struct Image {
     id: Option<ObjectId>,
     description: String,
     tag: Tag,
+    #[serde(default)]
     commands: Vec<Command>,
+    #[serde(default)]
+    status: ImageStatus,
+    #[serde(rename = "buildLog")]
+    #[serde(default)]
+    build_log: Vec<String>,
+    #[serde(rename = "dockerImageId")]
+    #[serde(default)]
+    docker_image_id: Option<String>,
+    #[serde(default)]
+    size: Option<i64>,
 }
 
 impl Image {
@@ -45,12 +66,105 @@ impl Image {
             description,
             tag,
             commands,
+            status: ImageStatus::Ready,
+            build_log: Vec::new(),
+            docker_image_id: None,
+            size: None,
+        }
+    }
+
+    /// Marks the image as waiting for its Docker image to be built in the background.
+    pub fn building(self) -> Self {
+        Self {
+            status: ImageStatus::Building,
+            build_log: Vec::new(),
+            docker_image_id: None,
+            size: None,
+            ..self
+        }
+    }
+
+    /// Records what Docker reported once the image is built.
+    pub fn built(
+        self,
+        build_log: Vec<String>,
+        docker_image_id: Option<String>,
+        size: Option<i64>,
+    ) -> Self {
+        Self {
+            status: ImageStatus::Ready,
+            build_log,
+            docker_image_id,
+            size,
+            ..self
         }
     }
 
+    /// Commands declared by the Docker image replace the ones with the same path, as they match
+    /// what the simulator really serves.
+    pub fn with_discovered_commands(self, discovered_commands: Vec<Command>) -> Self {
+        let mut commands = self
+            .commands
+            .into_iter()
+            .filter(|command| {
+                !discovered_commands
+                    .iter()
+                    .any(|discovered_command| discovered_command.path == command.path)
+            })
+            .collect::<Vec<_>>();
+        commands.extend(discovered_commands);
+
+        Self { commands, ..self }
+    }
+
+    /// Keeps the log of a build that did not succeed.
+    pub fn failed(self, build_log: Vec<String>) -> Self {
+        Self {
+            status: ImageStatus::Failed,
+            build_log,
+            ..self
+        }
+    }
+
+    /// Changes what can be edited once the Docker image is built.
+    pub fn edit(self, description: String, commands: Vec<Command>) -> Self {
+        Self {
+            description,
+            commands,
+            ..self
+        }
+    }
+
+    pub fn id(&self) -> Option<&ObjectId> {
+        self.id.as_ref()
+    }
+
     pub fn tag(&self) -> &Tag {
         &self.tag
     }
+
+    pub fn commands(&self) -> &Vec<Command> {
+        &self.commands
+    }
+
+    pub fn status(&self) -> ImageStatus {
+        self.status
+    }
+
+    pub fn build_log(&self) -> &Vec<String> {
+        &self.build_log
+    }
+
+    /// The fields set by a build. Images cannot be edited while they build.
+    pub fn build_document(&self) -> Result<mongodb::bson::Document, mongodb::bson::ser::Error> {
+        Ok(doc! {
+            "status": to_bson(&self.status)?,
+            "commands": commands_bson(&self.commands)?,
+            "buildLog": &self.build_log,
+            "dockerImageId": &self.docker_image_id,
+            "size": self.size,
+        })
+    }
 }
 
 impl Document for Image {
@@ -65,3 +179,22 @@ impl Document for Image {
         }
     }
 }
+
+impl TryFrom<Image> for mongodb::bson::Document {
+    type Error = mongodb::bson::ser::Error;
+
+    fn try_from(image: Image) -> Result<Self, Self::Error> {
+        Ok(doc! {
+            "description": image.description,
+            "tag": {
+                "name": image.tag.name,
+                "version": image.tag.version,
+            },
+            "commands": commands_bson(&image.commands)?,
+        })
+    }
+}
+
+fn commands_bson(commands: &[Command]) -> Result<Vec<Bson>, mongodb::bson::ser::Error> {
+    commands.iter().cloned().map(Bson::try_from).collect()
+}
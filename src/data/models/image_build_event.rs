@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+use super::ImageDTO;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum ImageBuildEvent {
+    Output { message: String },
+    Error { message: String },
+    Completed { image: ImageDTO },
+    Failed { message: String },
+}
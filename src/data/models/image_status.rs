@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// Where an image is in its build. Images saved before builds ran in the background are ready.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ImageStatus {
+    Building,
+    #[default]
+    Ready,
+    Failed,
+}
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// Whether a reference to an image uses the exact version it points to, or the latest version of
+/// the image with the same name.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ImageVersion {
+    #[default]
+    Pinned,
+    Latest,
+}
@@ -1,23 +1,48 @@
+pub use assertion_failure::AssertionFailure;
+pub use capture::Capture;
 pub use command::Command;
+pub use commands_diff::CommandsDiff;
 pub use environment::{Environment, EnvironmentDTO};
-pub use execution::{Execution, ExecutionDTO};
+pub use execution::{Execution, ExecutionDTO, ExecutionStatus};
+pub use expectation::Expectation;
 pub use image::{Image, ImageDTO};
+pub use image_build_event::ImageBuildEvent;
+pub use image_status::ImageStatus;
+pub use image_version::ImageVersion;
 pub use log_message::LogMessage;
+pub use runner::Runner;
 pub use scenario::{Scenario, ScenarioDTO};
+pub use scenario_playing_command::ScenarioPlayingCommand;
 pub use scenario_playing_event::ScenarioPlayingEvent;
 pub use simulator::{Simulator, SimulatorDTO};
 pub use step::{Step, StepDTO};
+pub use suite::{Suite, SuiteDTO};
+pub use suite_execution::{SuiteExecution, SuiteExecutionDTO, SuiteScenarioResult};
 pub use tag::Tag;
+pub use serializers::{deserialize_date, to_bson_date, to_stored_bson};
+pub use timing::{ExecutionTimings, Timing};
 
+mod assertion_failure;
+mod capture;
 mod command;
+mod commands_diff;
 mod environment;
 mod execution;
+mod expectation;
 mod image;
+mod image_build_event;
+mod image_status;
+mod image_version;
 mod log_message;
+mod runner;
 mod scenario;
+mod scenario_playing_command;
 mod scenario_playing_event;
 mod serializers;
 mod simulator;
 mod step;
+mod suite;
+mod suite_execution;
 mod tag;
+mod timing;
 
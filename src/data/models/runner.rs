@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// Process an execution runs in. A restarting server only recovers the executions it ran itself,
+/// as the command line runs its own against the same database.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Runner {
+    #[default]
+    Server,
+    Cli,
+}
@@ -27,7 +27,7 @@ impl From<Scenario> for ScenarioDTO {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Scenario {
     #[serde(alias = "_id")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -74,12 +74,20 @@ impl Document for Scenario {
     }
 }
 
-impl From<Scenario> for mongodb::bson::Document {
-    fn from(scenario: Scenario) -> Self {
-        doc! {
+impl TryFrom<Scenario> for mongodb::bson::Document {
+    type Error = mongodb::bson::ser::Error;
+
+    fn try_from(scenario: Scenario) -> Result<Self, Self::Error> {
+        let steps = scenario
+            .steps
+            .into_iter()
+            .map(mongodb::bson::Bson::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(doc! {
             "name": scenario.name,
             "description": scenario.description,
-            "steps": scenario.steps.into_iter().map(mongodb::bson::Bson::from).collect::<Vec<_>>(),
-        }
+            "steps": steps,
+        })
     }
 }
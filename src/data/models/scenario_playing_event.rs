@@ -1,22 +1,73 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use crate::data::models::LogMessage;
+use crate::data::models::{AssertionFailure, ImageStatus, LogMessage, Timing};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "type")]
 pub enum ScenarioPlayingEvent {
+    ImageMissing {
+        #[serde(rename = "imageId")]
+        image_id: String,
+    },
+    ImageNotReady {
+        #[serde(rename = "imageId")]
+        image_id: String,
+        status: ImageStatus,
+    },
+    SimulatorNotConfigured {
+        #[serde(rename = "imageId")]
+        image_id: String,
+    },
+    ContainerFailedToStart {
+        simulator: String,
+        message: String,
+    },
+    SimulatorVersionMismatch {
+        simulator: String,
+        expected: String,
+        actual: String,
+    },
+    SimulatorNotReady {
+        simulator: String,
+        #[serde(rename = "timeoutMs")]
+        timeout_ms: u64,
+    },
     ScenarioStarting,
     StepPassed {
         step: usize,
         message: String,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        timing: Option<Timing>,
     },
     StepFailed {
         step: usize,
         message: String,
         status: u16,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        failures: Vec<AssertionFailure>,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        timing: Option<Timing>,
+    },
+    VariableCaptured {
+        step: usize,
+        name: String,
+        value: Value,
+    },
+    StepTimedOut {
+        step: usize,
+        #[serde(rename = "timeoutMs")]
+        timeout_ms: u64,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        timing: Option<Timing>,
     },
     LogReceived {
         #[serde(rename = "logMessage")]
         log_message: LogMessage,
     },
+    ScenarioCancelled,
 }
@@ -1,5 +1,8 @@
+use chrono::{DateTime, Local, TimeZone};
 use mongodb::bson::oid::ObjectId;
-use serde::{Serialize, Serializer};
+use mongodb::bson::{to_bson_with_options, Bson, SerializerOptions};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 pub fn serialize_option_object_id<S>(
     option_object_id: &Option<ObjectId>,
@@ -20,3 +23,151 @@ pub fn serialize_object_id<S>(object_id: &ObjectId, serializer: S) -> Result<S::
 {
     object_id.serialize(serializer)
 }
+
+/// Dates are stored as BSON dates, which MongoDB compares in time order whatever their offset.
+pub fn to_bson_date(date: &DateTime<Local>) -> mongodb::bson::DateTime {
+    mongodb::bson::DateTime::from_millis(date.timestamp_millis())
+}
+
+/// Serializes a value the way it is stored, dates included, where `to_bson` would keep them as
+/// strings.
+pub fn to_stored_bson<T>(value: &T) -> Result<Bson, mongodb::bson::ser::Error>
+    where
+        T: Serialize + ?Sized,
+{
+    to_bson_with_options(
+        value,
+        SerializerOptions::builder().human_readable(false).build(),
+    )
+}
+
+/// Stores dates as BSON dates, while JSON keeps them as RFC 3339 strings.
+pub fn serialize_date<S>(date: &DateTime<Local>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+{
+    if serializer.is_human_readable() {
+        date.serialize(serializer)
+    } else {
+        to_bson_date(date).serialize(serializer)
+    }
+}
+
+pub fn serialize_option_date<S>(
+    option_date: &Option<DateTime<Local>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+{
+    match option_date {
+        Some(date) => serialize_date(date, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Dates are read from BSON dates, as well as from the RFC 3339 strings of JSON and of the
+/// documents saved before dates were stored as such.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredDate {
+    Date(mongodb::bson::DateTime),
+    Text(DateTime<Local>),
+}
+
+impl StoredDate {
+    fn into_local<E: Error>(self) -> Result<DateTime<Local>, E> {
+        match self {
+            StoredDate::Date(date) => Local
+                .timestamp_millis_opt(date.timestamp_millis())
+                .single()
+                .ok_or_else(|| E::custom(format!("Date {} is out of range", date))),
+            StoredDate::Text(date) => Ok(date),
+        }
+    }
+}
+
+pub fn deserialize_date<'de, D>(deserializer: D) -> Result<DateTime<Local>, D::Error>
+    where
+        D: Deserializer<'de>,
+{
+    StoredDate::deserialize(deserializer)?.into_local()
+}
+
+pub fn deserialize_option_date<'de, D>(
+    deserializer: D,
+) -> Result<Option<DateTime<Local>>, D::Error>
+    where
+        D: Deserializer<'de>,
+{
+    Option::<StoredDate>::deserialize(deserializer)?
+        .map(StoredDate::into_local)
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::{doc, from_document, Bson};
+
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Dated {
+        #[serde(serialize_with = "serialize_date")]
+        #[serde(deserialize_with = "deserialize_date")]
+        date: DateTime<Local>,
+        #[serde(serialize_with = "serialize_option_date")]
+        #[serde(deserialize_with = "deserialize_option_date")]
+        #[serde(default)]
+        ended_at: Option<DateTime<Local>>,
+    }
+
+    fn dated() -> Dated {
+        let date = DateTime::parse_from_rfc3339("2024-03-01T23:30:00.250+02:00").unwrap();
+
+        Dated {
+            date: date.with_timezone(&Local),
+            ended_at: Some(date.with_timezone(&Local)),
+        }
+    }
+
+    #[test]
+    fn stores_dates_as_bson_dates() {
+        let Bson::Document(document) = to_stored_bson(&dated()).unwrap() else {
+            panic!("Structs are stored as documents");
+        };
+        let date = Bson::DateTime(mongodb::bson::DateTime::from_millis(1709328600250));
+
+        assert_eq!(document.get("date"), Some(&date));
+        assert_eq!(document.get("ended_at"), Some(&date));
+
+        let stored = from_document::<Dated>(document).unwrap();
+
+        assert_eq!(stored.date, dated().date);
+        assert_eq!(stored.ended_at, dated().ended_at);
+    }
+
+    #[test]
+    fn writes_dates_as_strings_in_json() {
+        let json = serde_json::to_value(dated()).unwrap();
+
+        assert_eq!(
+            DateTime::parse_from_rfc3339(json["date"].as_str().unwrap()).unwrap(),
+            dated().date
+        );
+        assert_eq!(
+            serde_json::from_value::<Dated>(json).unwrap().ended_at,
+            dated().ended_at
+        );
+    }
+
+    #[test]
+    fn reads_dates_stored_as_strings() {
+        let document = doc! { "date": "2024-03-01T23:30:00.250+02:00", "ended_at": Bson::Null };
+
+        let dated = from_document::<Dated>(document).unwrap();
+
+        assert_eq!(dated.date.timestamp_millis(), 1709328600250);
+        assert_eq!(dated.ended_at, None);
+    }
+}
@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 use crate::data::repository::Document;
 
 use super::serializers::{serialize_object_id, serialize_option_object_id};
+use super::ImageVersion;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SimulatorDTO {
@@ -14,11 +15,12 @@ pub struct SimulatorDTO {
     #[serde(skip_serializing_if = "Option::is_none")]
     id: Option<String>,
     name: String,
-    port: u16,
     #[serde(rename = "environmentId")]
     environment_id: String,
     #[serde(rename = "imageId")]
     image_id: String,
+    #[serde(rename = "imageVersion")]
+    image_version: ImageVersion,
     configuration: HashMap<String, String>,
 }
 
@@ -27,9 +29,9 @@ impl From<Simulator> for SimulatorDTO {
         Self {
             id: simulator.id.as_ref().map(ToString::to_string),
             name: simulator.name,
-            port: simulator.port,
             environment_id: simulator.environment_id.to_string(),
             image_id: simulator.image_id.to_string(),
+            image_version: simulator.image_version,
             configuration: simulator.configuration,
         }
     }
@@ -42,46 +44,56 @@ pub struct Simulator {
     #[serde(serialize_with = "serialize_option_object_id")]
     id: Option<ObjectId>,
     name: String,
-    port: u16,
     #[serde(serialize_with = "serialize_object_id")]
     #[serde(rename = "environmentId")]
     environment_id: ObjectId,
     #[serde(serialize_with = "serialize_object_id")]
     #[serde(rename = "imageId")]
     image_id: ObjectId,
+    #[serde(rename = "imageVersion")]
+    #[serde(default)]
+    image_version: ImageVersion,
     configuration: HashMap<String, String>,
 }
 
 impl Simulator {
     pub fn new(
         name: String,
-        port: u16,
         environment_id: ObjectId,
         image_id: ObjectId,
+        image_version: ImageVersion,
         configuration: HashMap<String, String>,
     ) -> Self {
         Self {
             id: None,
             name,
-            port,
             environment_id,
             image_id,
+            image_version,
             configuration,
         }
     }
 
+    pub fn id(&self) -> Option<&ObjectId> {
+        self.id.as_ref()
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
 
-    pub fn port(&self) -> u16 {
-        self.port
+    pub fn environment_id(&self) -> &ObjectId {
+        &self.environment_id
     }
 
     pub fn image_id(&self) -> &ObjectId {
         &self.image_id
     }
 
+    pub fn image_version(&self) -> ImageVersion {
+        self.image_version
+    }
+
     pub fn configuration(&self) -> &HashMap<String, String> {
         &self.configuration
     }
@@ -104,9 +116,9 @@ impl From<Simulator> for mongodb::bson::Document {
     fn from(simulator: Simulator) -> Self {
         doc! {
             "name": simulator.name,
-            "port": simulator.port as u32,
             "environmentId": simulator.environment_id,
             "imageId": simulator.image_id,
+            "imageVersion": to_bson(&simulator.image_version).unwrap(),
             "configuration": to_bson(&simulator.configuration).unwrap(),
         }
     }
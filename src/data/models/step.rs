@@ -1,25 +1,36 @@
 use mongodb::bson::oid::ObjectId;
-use mongodb::bson::{bson, doc};
+use mongodb::bson::{bson, to_bson, Bson};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use super::serializers::serialize_object_id;
-use super::Command;
+use super::{Capture, Command, Expectation, ImageVersion};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct StepDTO {
     #[serde(rename = "imageId")]
     image_id: String,
+    #[serde(rename = "imageVersion")]
+    image_version: ImageVersion,
     command: Command,
     arguments: Value,
+    #[serde(rename = "timeoutMs")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout_ms: Option<u64>,
+    expectations: Vec<Expectation>,
+    captures: Vec<Capture>,
 }
 
 impl From<Step> for StepDTO {
     fn from(step: Step) -> Self {
         Self {
             image_id: step.image_id.to_string(),
+            image_version: step.image_version,
             command: step.command,
             arguments: step.arguments,
+            timeout_ms: step.timeout_ms,
+            expectations: step.expectations,
+            captures: step.captures,
         }
     }
 }
@@ -29,16 +40,35 @@ pub struct Step {
     #[serde(serialize_with = "serialize_object_id")]
     #[serde(rename = "imageId")]
     pub image_id: ObjectId,
+    #[serde(rename = "imageVersion")]
+    #[serde(default)]
+    pub image_version: ImageVersion,
     pub command: Command,
     pub arguments: Value,
+    #[serde(rename = "timeoutMs")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub expectations: Vec<Expectation>,
+    #[serde(default)]
+    pub captures: Vec<Capture>,
 }
 
-impl From<Step> for mongodb::bson::Bson {
-    fn from(step: Step) -> Self {
-        bson! ({
+/// Arguments, timeouts and expectations are written by users, and may hold numbers BSON cannot
+/// represent.
+impl TryFrom<Step> for mongodb::bson::Bson {
+    type Error = mongodb::bson::ser::Error;
+
+    fn try_from(step: Step) -> Result<Self, Self::Error> {
+        Ok(bson! ({
             "imageId": step.image_id,
-            "command": step.command,
-            "arguments": mongodb::bson::to_bson(&step.arguments).expect("Failed to serialize arguments"),
-        })
+            "imageVersion": to_bson(&step.image_version)?,
+            "command": Bson::try_from(step.command)?,
+            "arguments": to_bson(&step.arguments)?,
+            "timeoutMs": to_bson(&step.timeout_ms)?,
+            "expectations": to_bson(&step.expectations)?,
+            "captures": to_bson(&step.captures)?,
+        }))
     }
 }
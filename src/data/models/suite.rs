@@ -0,0 +1,78 @@
+use mongodb::bson::doc;
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::data::repository::Document;
+
+use super::serializers::serialize_option_object_id;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SuiteDTO {
+    #[serde(alias = "_id")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    name: String,
+    description: String,
+    #[serde(rename = "scenarioIds")]
+    scenario_ids: Vec<String>,
+}
+
+impl From<Suite> for SuiteDTO {
+    fn from(suite: Suite) -> Self {
+        Self {
+            id: suite.id.as_ref().map(ToString::to_string),
+            name: suite.name,
+            description: suite.description,
+            scenario_ids: suite.scenario_ids.iter().map(ToString::to_string).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Suite {
+    #[serde(alias = "_id")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "serialize_option_object_id")]
+    id: Option<ObjectId>,
+    name: String,
+    description: String,
+    #[serde(rename = "scenarioIds")]
+    scenario_ids: Vec<ObjectId>,
+}
+
+impl Suite {
+    pub fn id(&self) -> Option<&ObjectId> {
+        self.id.as_ref()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn scenario_ids(&self) -> &Vec<ObjectId> {
+        &self.scenario_ids
+    }
+}
+
+impl Document for Suite {
+    fn collection_name() -> &'static str {
+        "Suites"
+    }
+
+    fn with_id(self, id: ObjectId) -> Self {
+        Self {
+            id: Some(id),
+            ..self
+        }
+    }
+}
+
+impl From<Suite> for mongodb::bson::Document {
+    fn from(suite: Suite) -> Self {
+        doc! {
+            "name": suite.name,
+            "description": suite.description,
+            "scenarioIds": suite.scenario_ids,
+        }
+    }
+}
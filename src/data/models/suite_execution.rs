@@ -0,0 +1,332 @@
+use chrono::{DateTime, Local};
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, to_bson};
+use serde::{Deserialize, Serialize};
+
+use crate::data::repository::Document;
+
+use super::serializers::{
+    deserialize_date, serialize_date, serialize_object_id, serialize_option_object_id,
+};
+use super::{ExecutionStatus, Runner};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SuiteScenarioResultDTO {
+    #[serde(rename = "scenarioId")]
+    scenario_id: String,
+    #[serde(rename = "executionId")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    execution_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<ExecutionStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl From<SuiteScenarioResult> for SuiteScenarioResultDTO {
+    fn from(result: SuiteScenarioResult) -> Self {
+        Self {
+            scenario_id: result.scenario_id.to_string(),
+            execution_id: result.execution_id.as_ref().map(ToString::to_string),
+            status: result.status,
+            error: result.error,
+        }
+    }
+}
+
+/// Outcome of one scenario of a suite. Scenarios that could not be run at all have no execution,
+/// only an error.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SuiteScenarioResult {
+    #[serde(rename = "scenarioId")]
+    #[serde(serialize_with = "serialize_object_id")]
+    scenario_id: ObjectId,
+    #[serde(rename = "executionId")]
+    #[serde(serialize_with = "serialize_option_object_id")]
+    execution_id: Option<ObjectId>,
+    status: Option<ExecutionStatus>,
+    error: Option<String>,
+}
+
+impl SuiteScenarioResult {
+    pub fn executed(
+        scenario_id: ObjectId,
+        execution_id: ObjectId,
+        status: ExecutionStatus,
+    ) -> Self {
+        Self {
+            scenario_id,
+            execution_id: Some(execution_id),
+            status: Some(status),
+            error: None,
+        }
+    }
+
+    pub fn scenario_id(&self) -> &ObjectId {
+        &self.scenario_id
+    }
+
+    pub fn execution_id(&self) -> Option<&ObjectId> {
+        self.execution_id.as_ref()
+    }
+
+    pub fn status(&self) -> Option<ExecutionStatus> {
+        self.status
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn errored(scenario_id: ObjectId, error: String) -> Self {
+        Self {
+            scenario_id,
+            execution_id: None,
+            status: None,
+            error: Some(error),
+        }
+    }
+
+    /// Scenarios left over when their suite is cancelled.
+    pub fn cancelled(scenario_id: ObjectId) -> Self {
+        Self {
+            scenario_id,
+            execution_id: None,
+            status: Some(ExecutionStatus::Cancelled),
+            error: Some(String::from("Suite was cancelled")),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SuiteExecutionDTO {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(rename = "suiteId")]
+    suite_id: String,
+    #[serde(rename = "environmentId")]
+    environment_id: String,
+    timestamp: DateTime<Local>,
+    status: ExecutionStatus,
+    results: Vec<SuiteScenarioResultDTO>,
+    passed: usize,
+    failed: usize,
+    errored: usize,
+    cancelled: usize,
+}
+
+impl From<SuiteExecution> for SuiteExecutionDTO {
+    fn from(suite_execution: SuiteExecution) -> Self {
+        Self {
+            id: suite_execution.id.as_ref().map(ToString::to_string),
+            suite_id: suite_execution.suite_id.to_string(),
+            environment_id: suite_execution.environment_id.to_string(),
+            timestamp: suite_execution.timestamp,
+            status: suite_execution.status(),
+            results: suite_execution
+                .results
+                .into_iter()
+                .map(SuiteScenarioResultDTO::from)
+                .collect(),
+            passed: suite_execution.passed,
+            failed: suite_execution.failed,
+            errored: suite_execution.errored,
+            cancelled: suite_execution.cancelled,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SuiteExecution {
+    #[serde(alias = "_id")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "serialize_option_object_id")]
+    id: Option<ObjectId>,
+    #[serde(rename = "suiteId")]
+    suite_id: ObjectId,
+    #[serde(rename = "environmentId")]
+    environment_id: ObjectId,
+    #[serde(serialize_with = "serialize_date")]
+    #[serde(deserialize_with = "deserialize_date")]
+    timestamp: DateTime<Local>,
+    /// Suite executions saved before they had a status were only saved once over.
+    #[serde(default)]
+    status: Option<ExecutionStatus>,
+    results: Vec<SuiteScenarioResult>,
+    passed: usize,
+    failed: usize,
+    /// Suite executions saved before they counted them apart counted errored and cancelled
+    /// scenarios as failed.
+    #[serde(default)]
+    errored: usize,
+    #[serde(default)]
+    cancelled: usize,
+    /// Suite executions saved before they had a runner were all run by the server.
+    #[serde(default)]
+    runner: Runner,
+}
+
+impl SuiteExecution {
+    /// A suite execution starts running as soon as it is saved, its scenarios being queued one
+    /// after the other.
+    pub fn new(suite_id: ObjectId, environment_id: ObjectId, timestamp: DateTime<Local>) -> Self {
+        Self {
+            id: None,
+            suite_id,
+            environment_id,
+            timestamp,
+            status: Some(ExecutionStatus::Running),
+            results: Vec::new(),
+            passed: 0,
+            failed: 0,
+            errored: 0,
+            cancelled: 0,
+            runner: Runner::Server,
+        }
+    }
+
+    pub fn run_by(self, runner: Runner) -> Self {
+        Self { runner, ..self }
+    }
+
+    /// Adds the outcome of a scenario that is over. Scenarios that could not run count as errored.
+    pub fn record(mut self, result: SuiteScenarioResult) -> Self {
+        match result.status {
+            Some(ExecutionStatus::Passed) => self.passed += 1,
+            Some(ExecutionStatus::Failed) => self.failed += 1,
+            Some(ExecutionStatus::Cancelled) => self.cancelled += 1,
+            _ => self.errored += 1,
+        }
+
+        self.results.push(result);
+
+        self
+    }
+
+    /// Derives the outcome of the suite from the ones of its scenarios. Scenarios that could not
+    /// run or were cancelled make the whole suite errored.
+    pub fn finish(self, cancelled: bool) -> Self {
+        let status = if cancelled {
+            ExecutionStatus::Cancelled
+        } else if self.errored > 0 || self.cancelled > 0 {
+            ExecutionStatus::Errored
+        } else if self.failed > 0 {
+            ExecutionStatus::Failed
+        } else {
+            ExecutionStatus::Passed
+        };
+
+        Self {
+            status: Some(status),
+            ..self
+        }
+    }
+
+    /// The fields that change while the suite runs.
+    pub fn progress(&self) -> mongodb::bson::Document {
+        doc! {
+            "status": to_bson(&self.status()).unwrap(),
+            "results": to_bson(&self.results).unwrap(),
+            "passed": self.passed as i64,
+            "failed": self.failed as i64,
+            "errored": self.errored as i64,
+            "cancelled": self.cancelled as i64,
+        }
+    }
+
+    pub fn id(&self) -> Option<&ObjectId> {
+        self.id.as_ref()
+    }
+
+    pub fn timestamp(&self) -> DateTime<Local> {
+        self.timestamp
+    }
+
+    pub fn status(&self) -> ExecutionStatus {
+        self.status.unwrap_or(if self.failed > 0 {
+            ExecutionStatus::Failed
+        } else {
+            ExecutionStatus::Passed
+        })
+    }
+
+    pub fn results(&self) -> &Vec<SuiteScenarioResult> {
+        &self.results
+    }
+
+    pub fn passed(&self) -> usize {
+        self.passed
+    }
+
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+
+    pub fn errored(&self) -> usize {
+        self.errored
+    }
+
+    pub fn cancelled(&self) -> usize {
+        self.cancelled
+    }
+
+    pub fn runner(&self) -> Runner {
+        self.runner
+    }
+}
+
+impl Document for SuiteExecution {
+    fn collection_name() -> &'static str {
+        "SuiteExecutions"
+    }
+
+    fn with_id(self, id: ObjectId) -> Self {
+        Self {
+            id: Some(id),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_each_outcome_apart() {
+        let result =
+            |status| SuiteScenarioResult::executed(ObjectId::new(), ObjectId::new(), status);
+
+        let suite_execution = SuiteExecution::new(ObjectId::new(), ObjectId::new(), Local::now())
+            .record(result(ExecutionStatus::Passed))
+            .record(result(ExecutionStatus::Failed))
+            .record(result(ExecutionStatus::Errored))
+            .record(SuiteScenarioResult::errored(
+                ObjectId::new(),
+                String::from("No such scenario"),
+            ))
+            .record(SuiteScenarioResult::cancelled(ObjectId::new()))
+            .finish(false);
+
+        assert_eq!(suite_execution.passed(), 1);
+        assert_eq!(suite_execution.failed(), 1);
+        assert_eq!(suite_execution.errored(), 2);
+        assert_eq!(suite_execution.cancelled(), 1);
+        assert_eq!(suite_execution.status(), ExecutionStatus::Errored);
+    }
+
+    #[test]
+    fn fails_when_only_assertions_failed() {
+        let suite_execution = SuiteExecution::new(ObjectId::new(), ObjectId::new(), Local::now())
+            .record(SuiteScenarioResult::executed(
+                ObjectId::new(),
+                ObjectId::new(),
+                ExecutionStatus::Failed,
+            ))
+            .finish(false);
+
+        assert_eq!(suite_execution.failed(), 1);
+        assert_eq!(suite_execution.errored(), 0);
+        assert_eq!(suite_execution.status(), ExecutionStatus::Failed);
+    }
+}
@@ -0,0 +1,54 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use super::serializers::{deserialize_date, serialize_date};
+
+/// When something started and ended, along with how long it took.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct Timing {
+    #[serde(rename = "startedAt")]
+    #[serde(serialize_with = "serialize_date")]
+    #[serde(deserialize_with = "deserialize_date")]
+    pub started_at: DateTime<Local>,
+    #[serde(rename = "endedAt")]
+    #[serde(serialize_with = "serialize_date")]
+    #[serde(deserialize_with = "deserialize_date")]
+    pub ended_at: DateTime<Local>,
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+}
+
+impl Timing {
+    pub fn between(started_at: DateTime<Local>, ended_at: DateTime<Local>) -> Self {
+        Self {
+            started_at,
+            ended_at,
+            duration_ms: (ended_at - started_at)
+                .to_std()
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Times from the given start until now.
+    pub fn since(started_at: DateTime<Local>) -> Self {
+        Self::between(started_at, Local::now())
+    }
+}
+
+/// Where the time of an execution went. Phases the execution did not reach have no timing.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct ExecutionTimings {
+    /// Resolving the images of the steps and starting their simulators.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub setup: Option<Timing>,
+    /// Waiting for the simulators to be ready.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub readiness: Option<Timing>,
+    /// Running the steps, until the first one failing.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub steps: Option<Timing>,
+}
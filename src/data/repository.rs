@@ -2,7 +2,8 @@ use std::sync::Arc;
 
 use futures::TryStreamExt;
 use mongodb::{
-    bson::{doc, oid::ObjectId},
+    bson::{doc, from_document, oid::ObjectId, Bson},
+    options::FindOptions,
     Database,
 };
 use serde::{de::DeserializeOwned, Serialize};
@@ -14,6 +15,27 @@ pub trait Document {
     fn with_id(self, id: ObjectId) -> Self;
 }
 
+/// How the documents found by a query are sorted, paged and shaped.
+#[derive(Debug, Default, Clone)]
+pub struct QueryOptions {
+    pub sort: Option<mongodb::bson::Document>,
+    pub skip: Option<u64>,
+    pub limit: Option<i64>,
+    /// Fields left out by the projection must have a default to be deserialized.
+    pub projection: Option<mongodb::bson::Document>,
+}
+
+impl From<QueryOptions> for FindOptions {
+    fn from(options: QueryOptions) -> Self {
+        FindOptions::builder()
+            .sort(options.sort)
+            .skip(options.skip)
+            .limit(options.limit)
+            .projection(options.projection)
+            .build()
+    }
+}
+
 pub struct Repository {
     database: Arc<Database>,
 }
@@ -93,6 +115,44 @@ impl Repository {
         cursor.try_collect().await.map_err(Into::into)
     }
 
+    pub async fn query<T>(
+        &self,
+        document: mongodb::bson::Document,
+        options: QueryOptions,
+    ) -> Result<Vec<T>, DataError>
+        where
+            T: Document + Unpin + Send + Sync + Serialize + DeserializeOwned,
+    {
+        let collection = self.database.collection(T::collection_name());
+
+        let cursor = collection.find(document, FindOptions::from(options)).await?;
+
+        cursor.try_collect().await.map_err(Into::into)
+    }
+
+    /// Runs an aggregation pipeline over the collection of `T`, its results being read as `R`.
+    pub async fn aggregate<T, R>(
+        &self,
+        pipeline: Vec<mongodb::bson::Document>,
+    ) -> Result<Vec<R>, DataError>
+        where
+            T: Document + Unpin + Send + Sync + Serialize + DeserializeOwned,
+            R: DeserializeOwned,
+    {
+        let collection = self.database.collection::<T>(T::collection_name());
+
+        let documents = collection
+            .aggregate(pipeline, None)
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        documents
+            .into_iter()
+            .map(|document| from_document(document).map_err(Into::into))
+            .collect()
+    }
+
     pub async fn update<T>(
         &self,
         id: &ObjectId,
@@ -110,6 +170,29 @@ impl Repository {
         result.ok_or(DataError::NotFound)
     }
 
+    /// Appends a value to an array field of a document.
+    pub async fn push<T>(
+        &self,
+        id: &ObjectId,
+        field: &str,
+        value: impl Into<Bson>,
+    ) -> Result<(), DataError>
+        where
+            T: Document + Unpin + Send + Sync + Serialize + DeserializeOwned,
+    {
+        let collection = self.database.collection::<T>(T::collection_name());
+
+        let result = collection
+            .update_one(doc! { "_id" : id }, doc! { "$push" : { field : value.into() } }, None)
+            .await?;
+
+        if result.matched_count == 0 {
+            return Err(DataError::NotFound);
+        }
+
+        Ok(())
+    }
+
     pub async fn remove<T>(&self, id: &ObjectId) -> Result<T, DataError>
         where
             T: Document + Unpin + Send + Sync + Serialize + DeserializeOwned,
@@ -0,0 +1,40 @@
+use jsonschema::JSONSchema;
+use serde_json::Value;
+
+use super::step_variables;
+
+/// Checks that a schema declared by a command can be used to validate values.
+pub fn check_schema(schema: &Value) -> Result<(), String> {
+    JSONSchema::compile(schema)
+        .map(|_| ())
+        .map_err(|error| format!("Invalid schema: {}", error))
+}
+
+/// Validates the arguments of a step against the schema of its command. Strings referencing
+/// variables only get their value when the scenario runs, so they are not checked.
+pub fn validate_arguments(schema: &Value, arguments: &Value) -> Result<(), Vec<String>> {
+    let compiled_schema =
+        JSONSchema::compile(schema).map_err(|error| vec![format!("Invalid schema: {}", error)])?;
+
+    let errors = match compiled_schema.validate(arguments) {
+        Ok(()) => return Ok(()),
+        Err(errors) => errors
+            .filter(|error| match error.instance.as_ref() {
+                Value::String(text) => !step_variables::has_references(text),
+                _ => true,
+            })
+            .map(|error| {
+                let path = error.instance_path.to_string();
+                let path = if path.is_empty() { "/" } else { &path };
+
+                format!("{}: {}", path, error)
+            })
+            .collect::<Vec<_>>(),
+    };
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
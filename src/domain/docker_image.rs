@@ -2,15 +2,83 @@ use std::sync::Arc;
 
 use bollard::{
     Docker,
-    image::{BuildImageOptions, ListImagesOptions, RemoveImageOptions},
+    auth::DockerCredentials,
+    image::{
+        BuildImageOptions, CreateImageOptions, ImportImageOptions, ListImagesOptions,
+        RemoveImageOptions, TagImageOptions,
+    },
     models::ImageSummary,
 };
 use futures::TryStreamExt;
+use tokio::sync::mpsc::UnboundedSender;
 
-use crate::data::Tag;
+use crate::data::{Command, ImageBuildEvent, Tag};
 
+use super::check_schema;
 use super::error::DomainError;
 
+/// Label of the Docker image listing the commands served by the simulator, as a JSON array.
+const COMMANDS_LABEL: &str = "meta.commands";
+
+/// What Docker reported while building an image.
+pub struct BuildOutput {
+    pub log: Vec<String>,
+    pub docker_image_id: Option<String>,
+    pub size: Option<i64>,
+    /// The commands declared by the image itself, if it has a commands label.
+    pub commands: Option<Vec<Command>>,
+}
+
+/// Where the Docker image of an image comes from.
+pub enum ImageSource {
+    /// A tar.gz build context with a Dockerfile at its root.
+    BuildContext(Vec<u8>),
+    /// An archive made by `docker save`.
+    Archive(Vec<u8>),
+    /// An image to pull from a registry, then tag as a Meta image.
+    Registry {
+        reference: String,
+        credentials: Option<DockerCredentials>,
+    },
+}
+
+/// Collects the output of Docker, sending each line to the observer as it comes.
+struct OutputLog {
+    lines: Vec<String>,
+    observer: Option<UnboundedSender<ImageBuildEvent>>,
+}
+
+impl OutputLog {
+    fn push(&mut self, message: String) {
+        if message.trim().is_empty() {
+            return;
+        }
+
+        let message = message.trim_end().to_owned();
+
+        self.lines.push(message.clone());
+        self.notify(ImageBuildEvent::Output { message });
+    }
+
+    fn fail(mut self, message: String) -> DomainError {
+        self.lines.push(message.clone());
+        self.notify(ImageBuildEvent::Error {
+            message: message.clone(),
+        });
+
+        DomainError::ImageBuildFailed {
+            message,
+            log: self.lines,
+        }
+    }
+
+    fn notify(&self, event: ImageBuildEvent) {
+        if let Some(observer) = &self.observer {
+            observer.send(event).ok();
+        }
+    }
+}
+
 pub struct DockerImage {
     tag: Tag,
 }
@@ -20,29 +88,66 @@ impl DockerImage {
         Self { tag }
     }
 
+    /// Creates the Docker image tagged as a Meta image, sending each line of the output of Docker
+    /// to the observer as it comes.
     pub async fn create(
         docker: Arc<Docker>,
         tag: Tag,
-        image_bytes: Vec<u8>,
-    ) -> Result<Self, DomainError> {
-        let mut docker_build_info = docker.build_image(
-            BuildImageOptions {
-                t: tag.as_meta(),
-                rm: true,
-                ..Default::default()
-            },
-            None,
-            Some(image_bytes.into()),
-        );
+        source: ImageSource,
+        observer: Option<UnboundedSender<ImageBuildEvent>>,
+    ) -> Result<BuildOutput, DomainError> {
+        let mut log = OutputLog {
+            lines: Vec::new(),
+            observer,
+        };
+
+        let result = match source {
+            ImageSource::BuildContext(image_bytes) => {
+                build(&docker, &tag, image_bytes, &mut log).await
+            }
+            ImageSource::Archive(archive_bytes) => {
+                import(&docker, &tag, archive_bytes, &mut log).await
+            }
+            ImageSource::Registry {
+                reference,
+                credentials,
+            } => pull(&docker, &tag, &reference, credentials, &mut log).await,
+        };
 
-        while docker_build_info
-            .try_next()
+        if let Err(message) = result {
+            return Err(log.fail(message));
+        }
+
+        let inspection = docker
+            .inspect_image(&tag.as_meta())
             .await
-            .map_err(DomainError::Docker)?
-            .is_some()
-        {}
+            .map_err(DomainError::Docker)?;
+
+        let label = inspection
+            .config
+            .as_ref()
+            .and_then(|config| config.labels.as_ref())
+            .and_then(|labels| labels.get(COMMANDS_LABEL));
 
-        Ok(DockerImage { tag })
+        let commands = match label.map(|label| discover_commands(label)).transpose() {
+            Ok(commands) => commands,
+            Err(message) => return Err(log.fail(message)),
+        };
+
+        if let Some(commands) = &commands {
+            log.push(format!(
+                "Discovered {} command(s) from the {} label",
+                commands.len(),
+                COMMANDS_LABEL
+            ));
+        }
+
+        Ok(BuildOutput {
+            log: log.lines,
+            docker_image_id: inspection.id,
+            size: inspection.size,
+            commands,
+        })
     }
 
     pub async fn list(docker: Arc<Docker>) -> Result<Vec<ImageSummary>, DomainError> {
@@ -57,8 +162,9 @@ impl DockerImage {
             .map_err(DomainError::Docker)
     }
 
+    /// Removing an image that is not in Docker anymore is not an error.
     pub async fn delete(self, docker: Arc<Docker>) -> Result<(), DomainError> {
-        docker
+        let result = docker
             .remove_image(
                 &self.tag.as_meta(),
                 Some(RemoveImageOptions {
@@ -67,9 +173,178 @@ impl DockerImage {
                 }),
                 None,
             )
-            .await
-            .map_err(DomainError::Docker)?;
+            .await;
+
+        match result {
+            Ok(_)
+            | Err(bollard::errors::Error::DockerResponseServerError {
+                status_code: 404, ..
+            }) => Ok(()),
+            Err(error) => Err(DomainError::Docker(error)),
+        }
+    }
+}
+
+fn discover_commands(label: &str) -> Result<Vec<Command>, String> {
+    let commands = serde_json::from_str::<Vec<Command>>(label)
+        .map_err(|error| format!("Invalid {} label: {}", COMMANDS_LABEL, error))?;
+
+    for command in &commands {
+        for schema in [&command.arguments_schema, &command.response_schema]
+            .into_iter()
+            .flatten()
+        {
+            check_schema(schema).map_err(|error| {
+                format!(
+                    "Invalid {} label: command {}: {}",
+                    COMMANDS_LABEL, command.path, error
+                )
+            })?;
+        }
+    }
+
+    Ok(commands)
+}
+
+async fn build(
+    docker: &Docker,
+    tag: &Tag,
+    image_bytes: Vec<u8>,
+    log: &mut OutputLog,
+) -> Result<(), String> {
+    let mut build_info = docker.build_image(
+        BuildImageOptions {
+            t: tag.as_meta(),
+            rm: true,
+            ..Default::default()
+        },
+        None,
+        Some(image_bytes.into()),
+    );
+
+    while let Some(build_info) = build_info
+        .try_next()
+        .await
+        .map_err(|error| error.to_string())?
+    {
+        if let Some(message) = build_info.error {
+            return Err(message);
+        }
 
-        Ok(())
+        if let Some(message) = build_info.stream.or(build_info.status) {
+            log.push(message);
+        }
     }
+
+    Ok(())
+}
+
+/// Loads the first image of the archive, which keeps the tag it was saved with.
+async fn import(
+    docker: &Docker,
+    tag: &Tag,
+    archive_bytes: Vec<u8>,
+    log: &mut OutputLog,
+) -> Result<(), String> {
+    let mut import_info = docker.import_image(
+        ImportImageOptions { quiet: false },
+        archive_bytes.into(),
+        None,
+    );
+
+    let mut loaded_image = None;
+
+    while let Some(import_info) = import_info
+        .try_next()
+        .await
+        .map_err(|error| error.to_string())?
+    {
+        if let Some(message) = import_info.error {
+            return Err(message);
+        }
+
+        if let Some(message) = import_info.stream.or(import_info.status) {
+            if loaded_image.is_none() {
+                loaded_image = message.lines().find_map(|line| {
+                    line.strip_prefix("Loaded image: ")
+                        .or_else(|| line.strip_prefix("Loaded image ID: "))
+                        .map(|image| image.trim().to_owned())
+                });
+            }
+
+            log.push(message);
+        }
+    }
+
+    let loaded_image =
+        loaded_image.ok_or_else(|| String::from("The archive does not contain any image"))?;
+
+    tag_as_meta(docker, &loaded_image, tag, log).await
+}
+
+async fn pull(
+    docker: &Docker,
+    tag: &Tag,
+    reference: &str,
+    credentials: Option<DockerCredentials>,
+    log: &mut OutputLog,
+) -> Result<(), String> {
+    // Without a tag, Docker would pull every tag of the repository
+    let has_tag = reference.contains('@')
+        || reference
+            .rsplit('/')
+            .next()
+            .is_some_and(|last_part| last_part.contains(':'));
+
+    let mut create_info = docker.create_image(
+        Some(CreateImageOptions {
+            from_image: reference,
+            tag: if has_tag { "" } else { "latest" },
+            ..Default::default()
+        }),
+        None,
+        credentials,
+    );
+
+    while let Some(create_info) = create_info
+        .try_next()
+        .await
+        .map_err(|error| error.to_string())?
+    {
+        if let Some(message) = create_info.error {
+            return Err(message);
+        }
+
+        // Progress of the layers is left out, it would flood the log
+        if let Some(status) = create_info.status.filter(|_| create_info.progress.is_none()) {
+            match create_info.id {
+                Some(id) => log.push(format!("{}: {}", id, status)),
+                None => log.push(status),
+            }
+        }
+    }
+
+    tag_as_meta(docker, reference, tag, log).await
+}
+
+async fn tag_as_meta(
+    docker: &Docker,
+    image: &str,
+    tag: &Tag,
+    log: &mut OutputLog,
+) -> Result<(), String> {
+    docker
+        .tag_image(
+            image,
+            Some(TagImageOptions {
+                repo: format!("meta/{}", tag.name),
+                tag: tag.version.clone(),
+            }),
+        )
+        .await
+        .map_err(|error| error.to_string())?;
+
+    log.push(format!("Tagged {} as {}", image, tag.as_meta()));
+
+    Ok(())
 }
@@ -1,40 +1,264 @@
-use std::time::SystemTime;
 use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use bollard::Docker;
-use chrono::DateTime;
-use futures::{future::try_join_all, SinkExt};
-use mongodb::bson::doc;
+use chrono::Local;
+use futures::future::try_join_all;
 use mongodb::bson::oid::ObjectId;
-use scopeguard::defer;
+use mongodb::bson::{doc, to_bson};
+use scopeguard::ScopeGuard;
+use serde_json::Value;
 use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_util::sync::CancellationToken;
 use tracing::{trace, warn};
-use warp::ws::Message;
+use warp::hyper;
 
-use crate::data::{Execution, ScenarioPlayingEvent};
+use crate::data::{
+    Execution, ExecutionStatus, ExecutionTimings, Expectation, ImageStatus, ScenarioPlayingEvent,
+    Timing,
+};
 use crate::{
-    data::{Environment, Repository, Scenario, Simulator, Step},
-    domain::{docker_simulator::DockerSimulator, running_docker_simulator::RunningDockerSimulator},
+    data::{Environment, Image, Repository, Scenario, Simulator, Step},
+    domain::{
+        docker_simulator::DockerSimulator, image_versions,
+        running_docker_simulator::RunningDockerSimulator, step_assertions, step_variables,
+        ExecutionSettings,
+    },
 };
 
 use super::error::DomainError;
 
-pub struct DockerScenarioExecutor {}
+/// What running a step needs once the simulators are started.
+struct StepRun<'a> {
+    step: &'a Step,
+    simulator: &'a RunningDockerSimulator,
+    timeout: Duration,
+    response_schema: Option<Value>,
+}
+
+/// The image of each step, with the simulator started for it.
+type SimulatorsByImage = HashMap<ObjectId, (Image, RunningDockerSimulator)>;
+
+const READINESS_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone)]
+pub struct DockerScenarioExecutor {
+    docker: Arc<Docker>,
+    repository: Repository,
+    settings: ExecutionSettings,
+}
 
 impl DockerScenarioExecutor {
+    pub fn new(docker: Arc<Docker>, repository: Repository, settings: ExecutionSettings) -> Self {
+        Self {
+            docker,
+            repository,
+            settings,
+        }
+    }
+
+    /// Runs the scenario of an execution that was already saved, and saves its outcome. Setup
+    /// failures do not make this fail, they are recorded as an errored execution instead.
     pub async fn run_scenario_in_environment(
-        docker: Arc<Docker>,
+        &self,
         environment: &Environment,
         scenario: &Scenario,
-        repository: Repository,
-        mut web_socket: warp::ws::WebSocket,
+        execution: Execution,
+        observer: Option<UnboundedSender<ScenarioPlayingEvent>>,
+        cancellation: CancellationToken,
+    ) -> Result<Execution, DomainError> {
+        let execution_id = execution
+            .id()
+            .expect("Executions are saved before being run")
+            .to_owned();
+
+        let execution = execution.start(Local::now());
+
+        self.repository
+            .update::<Execution>(&execution_id, execution.clone().into())
+            .await?;
+
+        let (tx, mut rx) = mpsc::unbounded_channel::<ScenarioPlayingEvent>();
+        let tx = Arc::new(tx);
+
+        let repository = self.repository.clone();
+
+        let event_collector = tokio::spawn(async move {
+            let mut events = Vec::new();
+
+            while let Some(event) = rx.recv().await {
+                if let Some(observer) = &observer {
+                    observer.send(event.clone()).ok();
+                }
+
+                // Saving events as they happen keeps a trace of executions that get interrupted
+                if let Err(error) = record_event(&repository, &execution_id, &event).await {
+                    warn!(
+                        "Could not save event of execution {}: {:?}",
+                        execution_id, error
+                    );
+                }
+
+                events.push(event);
+            }
+
+            events
+        });
+
+        let mut timings = ExecutionTimings::default();
+
+        let result = self
+            .play_scenario(
+                environment,
+                scenario,
+                &execution_id.to_hex(),
+                tx.clone(),
+                cancellation,
+                &mut timings,
+            )
+            .await;
+
+        if let Some(event) = result.as_ref().err().and_then(setup_failure_event) {
+            tx.send(event).ok();
+        }
+
+        // Log streams end once their container is removed, which closes the channel
+        drop(tx);
+
+        let events = event_collector.await.unwrap_or_default();
+
+        let execution = execution.timed(timings);
+
+        let execution = match result {
+            Ok(()) => execution.finish(events, Local::now()),
+            Err(error) => {
+                warn!("Could not run scenario: {:?}", error);
+                execution.abort(events, Local::now())
+            }
+        };
+
+        self.repository
+            .update::<Execution>(&execution_id, execution.clone().into())
+            .await?;
+
+        Ok(execution)
+    }
+
+    async fn play_scenario(
+        &self,
+        environment: &Environment,
+        scenario: &Scenario,
+        run_id: &str,
+        tx: Arc<UnboundedSender<ScenarioPlayingEvent>>,
+        cancellation: CancellationToken,
+        timings: &mut ExecutionTimings,
     ) -> Result<(), DomainError> {
+        // Executions can be cancelled while they wait in the queue
+        if cancellation.is_cancelled() {
+            tx.send(ScenarioPlayingEvent::ScenarioCancelled).ok();
+
+            return Ok(());
+        }
+
         let steps = scenario.steps();
 
+        let setup_started_at = Local::now();
+        let setup = tokio::select! {
+            setup = self.set_up(steps, environment, run_id, tx.clone()) => Some(setup),
+            _ = cancellation.cancelled() => None,
+        };
+        timings.setup = Some(Timing::since(setup_started_at));
+
+        let Some(setup) = setup else {
+            // Simulators started before the cancellation are only known to Docker by now
+            DockerSimulator::remove_all(self.docker.clone(), run_id).await;
+            tx.send(ScenarioPlayingEvent::ScenarioCancelled).ok();
+
+            return Ok(());
+        };
+
+        let (step_image_ids, image_id_to_simulator) = setup?;
+
+        let running_simulators = image_id_to_simulator
+            .values()
+            .map(|(_, running_simulator)| running_simulator.clone())
+            .collect::<Vec<_>>();
+
+        // Makes sure the simulators are removed even if the scenario does not run to completion
+        let running_simulators = scopeguard::guard(running_simulators, |running_simulators| {
+            tokio::spawn(remove_simulators(running_simulators));
+        });
+
+        let readiness_started_at = Local::now();
+        let readiness = tokio::select! {
+            readiness = wait_for_simulators_to_be_ready(
+                running_simulators.clone(),
+                self.settings.readiness_timeout,
+                tx.clone(),
+            ) => Some(readiness),
+            _ = cancellation.cancelled() => None,
+        };
+        timings.readiness = Some(Timing::since(readiness_started_at));
+
+        let Some(readiness) = readiness else {
+            remove_simulators(ScopeGuard::into_inner(running_simulators)).await;
+            tx.send(ScenarioPlayingEvent::ScenarioCancelled).ok();
+
+            return Ok(());
+        };
+
+        readiness?;
+
+        let step_runs = steps
+            .iter()
+            .zip(&step_image_ids)
+            .map(|(step, image_id)| {
+                let (image, running_simulator) = image_id_to_simulator.get(image_id).unwrap();
+
+                StepRun {
+                    step,
+                    simulator: running_simulator,
+                    timeout: step_timeout(step, image, self.settings.command_timeout),
+                    response_schema: image
+                        .commands()
+                        .iter()
+                        .find(|command| command.path == step.command.path)
+                        .and_then(|command| command.response_schema.clone()),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let steps_started_at = Local::now();
+
+        tokio::select! {
+            _ = run_scenario(&step_runs, tx.clone()) => {}
+            _ = cancellation.cancelled() => {
+                trace!("Scenario cancelled");
+
+                tx.send(ScenarioPlayingEvent::ScenarioCancelled).ok();
+            }
+        }
+
+        timings.steps = Some(Timing::since(steps_started_at));
+
+        remove_simulators(ScopeGuard::into_inner(running_simulators)).await;
+
+        Ok(())
+    }
+
+    /// Resolves the images of the steps and starts a simulator for each of them.
+    async fn set_up(
+        &self,
+        steps: &[Step],
+        environment: &Environment,
+        run_id: &str,
+        tx: Arc<UnboundedSender<ScenarioPlayingEvent>>,
+    ) -> Result<(Vec<ObjectId>, SimulatorsByImage), DomainError> {
+        let step_image_ids = resolve_step_images(&self.repository, steps).await?;
+
         let unique_images =
-            steps
+            step_image_ids
                 .iter()
-                .map(|step| step.image_id)
+                .copied()
                 .fold(Vec::new(), |mut accumulator, current| {
                     if accumulator.contains(&current) {
                         accumulator
@@ -44,208 +268,432 @@ impl DockerScenarioExecutor {
                     }
                 });
 
-        let (tx, mut rx) = mpsc::unbounded_channel();
-        let tx = Arc::new(tx);
-
-        let image_id_to_running_simulator = instantiate_simulators(
+        let image_id_to_simulator = instantiate_simulators(
             unique_images,
-            repository.clone(),
-            docker.clone(),
+            run_id,
+            self.repository.clone(),
+            self.docker.clone(),
             environment,
-            tx.clone(),
+            tx,
         )
         .await?;
 
-        let running_simulators = image_id_to_running_simulator
-            .values()
-            .cloned()
-            .collect::<Vec<_>>();
-
-        defer! {
-            let running_simulators = running_simulators.clone();
-
-            tokio::spawn(async move {
-                for running_docker_simulator in running_simulators.clone() {
-                    if let Err(error) = running_docker_simulator.remove().await {
-                        warn!("Failed to remove simulator: {:?}", error);
-                    }
-                }
-            });
-        }
-
-        wait_for_simulators_to_be_ready(running_simulators.clone(), tx.clone()).await?;
+        Ok((step_image_ids, image_id_to_simulator))
+    }
+}
 
-        let scenario_id = scenario.id().unwrap().to_owned();
-        let environment_id = environment.id().unwrap().to_owned();
+async fn record_event(
+    repository: &Repository,
+    execution_id: &ObjectId,
+    event: &ScenarioPlayingEvent,
+) -> Result<(), DomainError> {
+    repository
+        .push::<Execution>(execution_id, "events", to_bson(event).unwrap())
+        .await?;
 
-        tokio::spawn(async move {
-            let mut events = Vec::new();
+    if let ScenarioPlayingEvent::ScenarioStarting = event {
+        repository
+            .update::<Execution>(
+                execution_id,
+                doc! { "status": to_bson(&ExecutionStatus::Running).unwrap() },
+            )
+            .await?;
+    }
 
-            while let Some(event) = rx.recv().await {
-                events.push(event.clone());
-
-                if let Err(err) = web_socket
-                    .send(Message::text(
-                        serde_json::to_string(&event).unwrap_or_default(),
-                    ))
-                    .await
-                {
-                    warn!("{:?}", err);
-                }
-            }
+    Ok(())
+}
 
-            repository
-                .clone()
-                .create::<Execution>(Execution::new(
-                    scenario_id,
-                    environment_id,
-                    DateTime::from(SystemTime::now()),
-                    events,
-                ))
-                .await
-                .ok();
-        });
+async fn remove_simulators(running_simulators: Vec<RunningDockerSimulator>) {
+    for running_docker_simulator in running_simulators {
+        if let Err(error) = running_docker_simulator.remove().await {
+            warn!("Failed to remove simulator: {:?}", error);
+        }
+    }
+}
 
-        let step_data = steps
-            .iter()
-            .map(|step| {
-                (
-                    step,
-                    image_id_to_running_simulator.get(&step.image_id).unwrap(),
-                )
+/// Infrastructure failures get their own events, so that they are not mistaken for failing steps.
+fn setup_failure_event(error: &DomainError) -> Option<ScenarioPlayingEvent> {
+    match error {
+        DomainError::ImageNotFound(image_id) => Some(ScenarioPlayingEvent::ImageMissing {
+            image_id: image_id.clone(),
+        }),
+        DomainError::ImageNotReady { image_id, status } => {
+            Some(ScenarioPlayingEvent::ImageNotReady {
+                image_id: image_id.clone(),
+                status: *status,
+            })
+        }
+        DomainError::SimulatorNotFound(image_id) => {
+            Some(ScenarioPlayingEvent::SimulatorNotConfigured {
+                image_id: image_id.clone(),
+            })
+        }
+        DomainError::ContainerFailedToStart { simulator, message } => {
+            Some(ScenarioPlayingEvent::ContainerFailedToStart {
+                simulator: simulator.clone(),
+                message: message.clone(),
             })
-            .collect::<Vec<_>>();
-
-        if let Err(error) = run_scenario(&step_data, tx.clone()).await {
-            match error {
-                DomainError::SimulatorCommandFailed {
-                    step,
-                    message,
-                    status,
-                } => tx
-                    .send(ScenarioPlayingEvent::StepFailed {
-                        step,
-                        message,
-                        status: status.as_u16(),
-                    })
-                    .ok(),
-                _ => Some(()),
-            };
         }
+        DomainError::SimulatorVersionMismatch {
+            simulator,
+            expected,
+            actual,
+        } => Some(ScenarioPlayingEvent::SimulatorVersionMismatch {
+            simulator: simulator.clone(),
+            expected: expected.clone(),
+            actual: actual.clone(),
+        }),
+        DomainError::SimulatorNotReady { simulator, timeout } => {
+            Some(ScenarioPlayingEvent::SimulatorNotReady {
+                simulator: simulator.clone(),
+                timeout_ms: timeout.as_millis() as u64,
+            })
+        }
+        _ => None,
+    }
+}
 
-        Ok(())
+fn step_failure_event(error: DomainError, timing: Timing) -> Option<ScenarioPlayingEvent> {
+    match error {
+        DomainError::SimulatorCommandFailed {
+            step,
+            message,
+            status,
+        }
+        | DomainError::StepVariablesFailed {
+            step,
+            message,
+            status,
+        } => Some(ScenarioPlayingEvent::StepFailed {
+            step,
+            message,
+            status: status.as_u16(),
+            failures: Vec::new(),
+            timing: Some(timing),
+        }),
+        DomainError::StepAssertionsFailed {
+            step,
+            message,
+            status,
+            failures,
+        } => Some(ScenarioPlayingEvent::StepFailed {
+            step,
+            message,
+            status: status.as_u16(),
+            failures,
+            timing: Some(timing),
+        }),
+        DomainError::SimulatorCommandTimedOut { step, timeout } => {
+            Some(ScenarioPlayingEvent::StepTimedOut {
+                step,
+                timeout_ms: timeout.as_millis() as u64,
+                timing: Some(timing),
+            })
+        }
+        _ => None,
     }
 }
 
+/// The step's own timeout wins over the one declared by the image for the command, which in turn
+/// wins over the default one.
+fn step_timeout(step: &Step, image: &Image, default: Duration) -> Duration {
+    step.timeout_ms
+        .or_else(|| {
+            image
+                .commands()
+                .iter()
+                .find(|command| command.path == step.command.path)
+                .and_then(|command| command.timeout_ms)
+        })
+        .map(Duration::from_millis)
+        .unwrap_or(default)
+}
+
 async fn instantiate_simulators(
     images: Vec<ObjectId>,
+    run_id: &str,
     repository: Repository,
     docker: Arc<Docker>,
     environment: &Environment,
     tx: Arc<UnboundedSender<ScenarioPlayingEvent>>,
-) -> Result<HashMap<ObjectId, RunningDockerSimulator>, DomainError> {
+) -> Result<SimulatorsByImage, DomainError> {
     let mut image_id_to_running_docker_simulator = HashMap::new();
 
     for image_id in images {
-        let simulator = repository
-            .find::<Simulator>(doc! {
-                "imageId": image_id,
-                "environmentId": environment.id().unwrap()
-            })
-            .await?;
+        let result = instantiate_simulator(
+            image_id,
+            run_id,
+            &repository,
+            docker.clone(),
+            environment,
+            tx.clone(),
+        )
+        .await;
 
-        let simulator = match simulator.first() {
-            Some(simulator) => simulator,
-            None => return Err(DomainError::SimulatorNotFound(image_id.to_string())),
-        };
+        match result {
+            Ok(simulator) => {
+                image_id_to_running_docker_simulator.insert(image_id, simulator);
+            }
+            Err(error) => {
+                // Simulators that did start would otherwise be left behind
+                remove_simulators(
+                    image_id_to_running_docker_simulator
+                        .into_values()
+                        .map(|(_, running_simulator)| running_simulator)
+                        .collect(),
+                )
+                .await;
 
-        let image = repository.find_by_id(simulator.image_id()).await?;
+                return Err(error);
+            }
+        }
+    }
 
-        let image = match image {
-            Some(image) => image,
-            None => return Err(DomainError::ImageNotFound(simulator.image_id().to_string())),
-        };
+    Ok(image_id_to_running_docker_simulator)
+}
+
+/// Steps following the latest version of their image run against the version that is the latest
+/// when the scenario starts.
+async fn resolve_step_images(
+    repository: &Repository,
+    steps: &[Step],
+) -> Result<Vec<ObjectId>, DomainError> {
+    let mut image_ids = Vec::new();
+
+    for step in steps {
+        let image = image_versions::resolve_image(repository, &step.image_id, step.image_version)
+            .await?
+            .ok_or_else(|| DomainError::ImageNotFound(step.image_id.to_string()))?;
+
+        if image.status() != ImageStatus::Ready {
+            return Err(DomainError::ImageNotReady {
+                image_id: image.id().unwrap().to_string(),
+                status: image.status(),
+            });
+        }
+
+        image_ids.push(*image.id().unwrap());
+    }
+
+    Ok(image_ids)
+}
+
+async fn instantiate_simulator(
+    image_id: ObjectId,
+    run_id: &str,
+    repository: &Repository,
+    docker: Arc<Docker>,
+    environment: &Environment,
+    tx: Arc<UnboundedSender<ScenarioPlayingEvent>>,
+) -> Result<(Image, RunningDockerSimulator), DomainError> {
+    let image = repository
+        .find_by_id::<Image>(&image_id)
+        .await?
+        .ok_or_else(|| DomainError::ImageNotFound(image_id.to_string()))?;
+
+    // The simulator of the environment may reference any version of the image
+    let version_ids = image_versions::versions(repository, &image.tag().name)
+        .await?
+        .iter()
+        .filter_map(|version| version.id().copied())
+        .collect::<Vec<_>>();
+
+    let candidates = repository
+        .find::<Simulator>(doc! {
+            "imageId": { "$in": version_ids },
+            "environmentId": environment.id().unwrap()
+        })
+        .await?;
 
-        let docker_container =
-            DockerSimulator::create(docker.clone(), environment, simulator, &image).await?;
+    // Several simulators may run versions of the image, the one running this exact version is used
+    let mut mismatch = None;
+    let mut matching = None;
 
-        let docker_simulator = docker_container.start(Some(tx.clone())).await?;
+    for candidate in candidates {
+        let candidate_image = image_versions::resolve_image(
+            repository,
+            candidate.image_id(),
+            candidate.image_version(),
+        )
+        .await?
+        .ok_or_else(|| DomainError::ImageNotFound(candidate.image_id().to_string()))?;
 
-        image_id_to_running_docker_simulator.insert(image_id, docker_simulator);
+        if candidate_image.id() == image.id() {
+            matching = Some(candidate);
+            break;
+        }
+
+        mismatch.get_or_insert(DomainError::SimulatorVersionMismatch {
+            simulator: candidate.name().to_owned(),
+            expected: image.tag().version.clone(),
+            actual: candidate_image.tag().version.clone(),
+        });
     }
 
-    Ok(image_id_to_running_docker_simulator)
+    let Some(simulator) = matching else {
+        return Err(
+            mismatch.unwrap_or_else(|| DomainError::SimulatorNotFound(image_id.to_string()))
+        );
+    };
+
+    let docker_container = DockerSimulator::create(docker, environment, &simulator, &image, run_id)
+        .await
+        .map_err(|error| DomainError::ContainerFailedToStart {
+            simulator: simulator.name().to_owned(),
+            message: error.to_string(),
+        })?;
+
+    let docker_simulator = docker_container.start(Some(tx)).await?;
+
+    Ok((image, docker_simulator))
 }
 
 async fn wait_for_simulators_to_be_ready(
     running_docker_simulators: Vec<RunningDockerSimulator>,
+    readiness_timeout: Duration,
     tx: Arc<UnboundedSender<ScenarioPlayingEvent>>,
 ) -> Result<(), DomainError> {
     let ready_futures = running_docker_simulators
         .into_iter()
-        .map(|running_simulator| {
-            tokio::spawn(async move {
-                for _ in 0..30 {
-                    if running_simulator.is_ready().await {
-                        return Ok(());
-                    }
-
-                    tokio::time::sleep(Duration::from_secs(1)).await;
+        .map(|running_simulator| async move {
+            let ready = async {
+                while !running_simulator.is_ready().await {
+                    tokio::time::sleep(READINESS_INTERVAL).await;
                 }
+            };
 
-                Err(DomainError::SimulatorNotReady(format!(
-                    "Simulator {} was not ready within 30 seconds",
-                    running_simulator.name()
-                )))
-            })
-        })
-        .collect::<Vec<_>>();
+            tokio::time::timeout(readiness_timeout, ready)
+                .await
+                .map_err(|_| DomainError::SimulatorNotReady {
+                    simulator: running_simulator.name().to_owned(),
+                    timeout: readiness_timeout,
+                })
+        });
 
-    let result = try_join_all(ready_futures).await.map_err(|error| {
-        DomainError::SimulatorNotReady(format!(
-            "Error while waiting for simulators to be ready: {}",
-            error
-        ))
-    })?;
+    try_join_all(ready_futures).await?;
 
     tx.send(ScenarioPlayingEvent::ScenarioStarting).ok();
 
-    result.into_iter().collect()
+    Ok(())
 }
 
-async fn run_scenario(
-    step_data: &[(&Step, &RunningDockerSimulator)],
-    tx: Arc<UnboundedSender<ScenarioPlayingEvent>>,
-) -> Result<(), DomainError> {
-    for (i, (step, running_docker_simulator)) in step_data.iter().enumerate() {
-        trace!(
-            "Step #{}: Command: {:?}, Arguments: {:?}",
-            i + 1,
-            step.command,
-            step.arguments
-        );
+/// Runs the steps in order, until the first one failing.
+async fn run_scenario(step_runs: &[StepRun<'_>], tx: Arc<UnboundedSender<ScenarioPlayingEvent>>) {
+    let mut variables = HashMap::new();
 
-        let command_result = running_docker_simulator
-            .execute_command(i + 1, &step.command.path, &step.arguments)
-            .await;
+    for (i, step_run) in step_runs.iter().enumerate() {
+        let started_at = Local::now();
+        let result = run_step(i + 1, step_run, &variables).await;
+        let timing = Timing::since(started_at);
 
-        match command_result {
-            Ok(response) => {
-                tx.send(ScenarioPlayingEvent::StepPassed {
-                    step: i + 1,
-                    message: response,
-                })
-                .ok();
-            }
+        let (message, captured) = match result {
+            Ok(outcome) => outcome,
             Err(error) => {
-                if matches!(error, DomainError::SimulatorCommandFailed { .. }) {
-                    return Err(error);
+                if let Some(event) = step_failure_event(error, timing) {
+                    tx.send(event).ok();
                 }
+
+                return;
             }
         };
 
-        trace!("Ran command {:?}", step.command);
+        tx.send(ScenarioPlayingEvent::StepPassed {
+            step: i + 1,
+            message,
+            timing: Some(timing),
+        })
+        .ok();
+
+        for (name, value) in captured {
+            tx.send(ScenarioPlayingEvent::VariableCaptured {
+                step: i + 1,
+                name: name.clone(),
+                value: value.clone(),
+            })
+            .ok();
+
+            variables.insert(name, value);
+        }
+
+        trace!("Ran command {:?}", step_run.step.command);
     }
+}
 
-    Ok(())
+/// Runs a step, giving back the body of its response and the variables it captured.
+async fn run_step(
+    step_number: usize,
+    step_run: &StepRun<'_>,
+    variables: &HashMap<String, Value>,
+) -> Result<(String, Vec<(String, Value)>), DomainError> {
+    let step = step_run.step;
+
+    let arguments = step_variables::render(&step.arguments, variables).map_err(|message| {
+        DomainError::StepVariablesFailed {
+            step: step_number,
+            message,
+            status: hyper::StatusCode::BAD_REQUEST,
+        }
+    })?;
+
+    trace!(
+        "Step #{}: Command: {:?}, Arguments: {:?}",
+        step_number,
+        step.command,
+        arguments
+    );
+
+    let response = step_run
+        .simulator
+        .execute_command(
+            step_number,
+            &step.command.path,
+            &arguments,
+            step_run.timeout,
+        )
+        .await?;
+
+    let expects_status = step
+        .expectations
+        .iter()
+        .any(|expectation| matches!(expectation, Expectation::Status { .. }));
+
+    if !expects_status && !response.status.is_success() {
+        return Err(DomainError::SimulatorCommandFailed {
+            step: step_number,
+            message: response.body,
+            status: response.status,
+        });
+    }
+
+    let mut failures = step_assertions::verify(&step.expectations, &response);
+
+    // Responses must also match the schema declared by the image for the command
+    if let Some(schema) = &step_run.response_schema {
+        failures.extend(step_assertions::verify(
+            &[Expectation::Schema {
+                schema: schema.clone(),
+            }],
+            &response,
+        ));
+    }
+
+    if !failures.is_empty() {
+        return Err(DomainError::StepAssertionsFailed {
+            step: step_number,
+            message: response.body,
+            status: response.status,
+            failures,
+        });
+    }
+
+    let captured =
+        step_variables::capture(&step.captures, &response.json_body()).map_err(|message| {
+            DomainError::StepVariablesFailed {
+                step: step_number,
+                message,
+                status: response.status,
+            }
+        })?;
+
+    Ok((response.body, captured))
 }
@@ -6,11 +6,12 @@ use bollard::{
     Docker,
     models::HostConfig,
 };
-use bollard::container::{LogOutput, LogsOptions, RemoveContainerOptions};
+use bollard::container::{ListContainersOptions, LogOutput, LogsOptions, RemoveContainerOptions};
 use bollard::models::PortBinding;
-use chrono::Local;
+use chrono::{DateTime, Local};
 use futures::stream::StreamExt;
 use tokio::sync::mpsc::UnboundedSender;
+use tracing::warn;
 
 use crate::data::{Environment, Image, Simulator};
 use crate::data::{LogMessage, ScenarioPlayingEvent};
@@ -29,8 +30,10 @@ impl DockerSimulator {
         environment: &Environment,
         simulator: &Simulator,
         image: &Image,
+        run_id: &str,
     ) -> Result<DockerSimulator, DomainError> {
-        let container_name = format!("{}-{}", environment.name(), simulator.name());
+        // Each run gets its own containers, so that runs of the same environment do not collide
+        let container_name = format!("{}-{}-{}", environment.name(), simulator.name(), run_id);
 
         docker
             .create_container(
@@ -45,9 +48,10 @@ impl DockerSimulator {
                     host_config: Some(HostConfig {
                         port_bindings: Some(HashMap::from([(
                             String::from("3000/tcp"),
+                            // Lets Docker pick a free host port, read back once started
                             Some(vec![PortBinding {
                                 host_ip: Some(String::from("127.0.0.1")),
-                                host_port: Some(format!("{}", simulator.port())),
+                                host_port: None,
                             }]),
                         )])),
                         ..Default::default()
@@ -71,15 +75,45 @@ impl DockerSimulator {
         })
     }
 
+    /// Removes the containers of a run, including the ones that were created but not started.
+    pub async fn remove_all(docker: Arc<Docker>, run_id: &str) {
+        let containers = docker
+            .list_containers(Some(ListContainersOptions {
+                all: true,
+                filters: HashMap::from([("name", vec![run_id])]),
+                ..Default::default()
+            }))
+            .await;
+
+        let containers = match containers {
+            Ok(containers) => containers,
+            Err(error) => {
+                warn!("Could not list the containers of run {}: {:?}", run_id, error);
+                return;
+            }
+        };
+
+        for container_id in containers.into_iter().filter_map(|container| container.id) {
+            if let Err(error) = docker
+                .remove_container(
+                    &container_id,
+                    Some(RemoveContainerOptions {
+                        force: true,
+                        ..Default::default()
+                    }),
+                )
+                .await
+            {
+                warn!("Failed to remove simulator: {:?}", error);
+            }
+        }
+    }
+
     pub async fn start(
         self,
         tx: Option<Arc<UnboundedSender<ScenarioPlayingEvent>>>,
     ) -> Result<RunningDockerSimulator, DomainError> {
-        self.docker
-            .start_container(self.container_name(), None::<StartContainerOptions<String>>)
-            .await?;
-
-        match get_exposed_port_for_container(self.docker.clone(), self.container_name()).await {
+        match self.start_container().await {
             Ok(port) => {
                 if let Some(sender) = tx {
                     self.attach_logs(sender);
@@ -91,7 +125,8 @@ impl DockerSimulator {
                     self.docker,
                 ))
             }
-            Err(_) => {
+            Err(message) => {
+                // The container was created, so it would otherwise be left behind
                 self.docker
                     .remove_container(
                         self.container_name(),
@@ -103,13 +138,26 @@ impl DockerSimulator {
                     .await
                     .ok();
 
-                Err(DomainError::SimulatorNotReady(String::from(
-                    "No port exposed by simulator",
-                )))
+                Err(DomainError::ContainerFailedToStart {
+                    simulator: self.simulator_name().to_owned(),
+                    message,
+                })
             }
         }
     }
 
+    async fn start_container(&self) -> Result<u16, String> {
+        self.docker
+            .start_container(self.container_name(), None::<StartContainerOptions<String>>)
+            .await
+            .map_err(|error| error.to_string())?;
+
+        get_exposed_port_for_container(self.docker.clone(), self.container_name())
+            .await
+            .map_err(|error| error.to_string())?
+            .ok_or_else(|| String::from("No port exposed by simulator"))
+    }
+
     fn attach_logs(&self, tx: Arc<UnboundedSender<ScenarioPlayingEvent>>) {
         let docker = self.docker.clone();
         let container_name = self.container_name().to_owned();
@@ -137,15 +185,8 @@ impl DockerSimulator {
                         LogOutput::StdErr { message } => (message, true),
                     };
 
-                    // TODO: Stop crashing
-                    let message_with_timestamp =
-                        String::from_utf8_lossy(message.as_ref()).to_string();
-                    let split_index = message_with_timestamp.find(' ').expect("No timestamp");
-                    let (timestamp, message) = message_with_timestamp.split_at(split_index);
-                    let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp)
-                        .expect("Invalid timestamp")
-                        .with_timezone(&Local);
-                    let mut message = message[1..].to_owned();
+                    let message = String::from_utf8_lossy(message.as_ref()).to_string();
+                    let (timestamp, mut message) = split_timestamp(&message);
 
                     if message.ends_with('\n') {
                         message.pop();
@@ -174,10 +215,22 @@ impl DockerSimulator {
     }
 }
 
+/// Docker prefixes log lines with their timestamp, but lines that lack a valid one are kept as they
+/// are, timestamped with the time they were received.
+fn split_timestamp(line: &str) -> (DateTime<Local>, String) {
+    line.split_once(' ')
+        .and_then(|(timestamp, message)| {
+            DateTime::parse_from_rfc3339(timestamp)
+                .ok()
+                .map(|timestamp| (timestamp.with_timezone(&Local), message.to_owned()))
+        })
+        .unwrap_or_else(|| (Local::now(), line.to_owned()))
+}
+
 async fn get_exposed_port_for_container(
     docker: Arc<Docker>,
     container_name: &str,
-) -> Result<u16, DomainError> {
+) -> Result<Option<u16>, DomainError> {
     let container_inspect_response = docker.inspect_container(container_name, None).await?;
 
     let option_port = container_inspect_response
@@ -195,11 +248,5 @@ async fn get_exposed_port_for_container(
         })
         .and_then(|port| port.parse::<u16>().ok());
 
-    match option_port {
-        Some(port) => Ok(port),
-        None => Err(DomainError::SimulatorNotFound(format!(
-            "Could not find exposed port for simulator {}",
-            container_name
-        ))),
-    }
+    Ok(option_port)
 }
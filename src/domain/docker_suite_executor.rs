@@ -0,0 +1,189 @@
+use std::sync::Arc;
+
+use bollard::Docker;
+use chrono::Local;
+use mongodb::bson::oid::ObjectId;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+use crate::data::{
+    Environment, Execution, Repository, Runner, Scenario, ScenarioPlayingEvent, Suite,
+    SuiteExecution, SuiteScenarioResult,
+};
+use crate::domain::{
+    execution_queue, DockerScenarioExecutor, ExecutionRegistry, ExecutionSettings,
+};
+
+use super::error::DomainError;
+
+pub struct DockerSuiteExecutor {
+    scenario_executor: DockerScenarioExecutor,
+    repository: Repository,
+    registry: Option<ExecutionRegistry>,
+    slots: Option<Arc<Semaphore>>,
+}
+
+impl DockerSuiteExecutor {
+    pub fn new(docker: Arc<Docker>, repository: Repository, settings: ExecutionSettings) -> Self {
+        Self {
+            scenario_executor: DockerScenarioExecutor::new(docker, repository.clone(), settings),
+            repository,
+            registry: None,
+            slots: None,
+        }
+    }
+
+    /// Makes each scenario wait for an execution slot, and registers it so that clients can
+    /// follow or cancel it like any other execution.
+    pub fn queued(self, registry: ExecutionRegistry, slots: Arc<Semaphore>) -> Self {
+        Self {
+            registry: Some(registry),
+            slots: Some(slots),
+            ..self
+        }
+    }
+
+    /// Runs the scenarios of a suite execution that was already saved, one after the other, saving
+    /// its progress after each of them. A scenario that fails, or that cannot be run at all, does
+    /// not prevent the following ones from running. The observer receives the events of each
+    /// scenario along with its ID.
+    pub async fn run_suite_in_environment(
+        &self,
+        environment: &Environment,
+        suite: &Suite,
+        mut suite_execution: SuiteExecution,
+        observer: Option<UnboundedSender<(ObjectId, ScenarioPlayingEvent)>>,
+        cancellation: CancellationToken,
+    ) -> Result<SuiteExecution, DomainError> {
+        let suite_execution_id = suite_execution
+            .id()
+            .expect("Suite executions are saved before being run")
+            .to_owned();
+
+        for scenario_id in suite.scenario_ids() {
+            let result = if cancellation.is_cancelled() {
+                SuiteScenarioResult::cancelled(*scenario_id)
+            } else {
+                self.run_scenario(
+                    environment,
+                    scenario_id,
+                    suite_execution.runner(),
+                    &observer,
+                    &cancellation,
+                )
+                .await
+                .unwrap_or_else(|error| {
+                    warn!("Could not run scenario {}: {:?}", scenario_id, error);
+                    SuiteScenarioResult::errored(*scenario_id, error.to_string())
+                })
+            };
+
+            suite_execution = suite_execution.record(result);
+
+            if let Err(error) = self
+                .repository
+                .update::<SuiteExecution>(&suite_execution_id, suite_execution.progress())
+                .await
+            {
+                warn!(
+                    "Could not save progress of suite execution {}: {:?}",
+                    suite_execution_id, error
+                );
+            }
+        }
+
+        let suite_execution = suite_execution.finish(cancellation.is_cancelled());
+
+        self.repository
+            .update::<SuiteExecution>(&suite_execution_id, suite_execution.progress())
+            .await?;
+
+        Ok(suite_execution)
+    }
+
+    async fn run_scenario(
+        &self,
+        environment: &Environment,
+        scenario_id: &ObjectId,
+        runner: Runner,
+        observer: &Option<UnboundedSender<(ObjectId, ScenarioPlayingEvent)>>,
+        cancellation: &CancellationToken,
+    ) -> Result<SuiteScenarioResult, DomainError> {
+        let Some(scenario) = self.repository.find_by_id::<Scenario>(scenario_id).await? else {
+            return Ok(SuiteScenarioResult::errored(
+                *scenario_id,
+                String::from("Scenario not found"),
+            ));
+        };
+
+        let execution = self
+            .repository
+            .create(
+                Execution::new(
+                    *scenario_id,
+                    *environment.id().unwrap(),
+                    scenario.steps().clone(),
+                    Local::now(),
+                )
+                .run_by(runner),
+            )
+            .await?;
+        let execution_id = execution.id().unwrap().to_owned();
+
+        let (registry_observer, cancellation) = match &self.registry {
+            Some(registry) => {
+                let (tx, cancellation) =
+                    registry.register(execution_id, cancellation.child_token());
+                (Some(tx), cancellation)
+            }
+            None => (None, cancellation.child_token()),
+        };
+
+        let scenario_observer = if registry_observer.is_some() || observer.is_some() {
+            let (tx, mut rx) = mpsc::unbounded_channel::<ScenarioPlayingEvent>();
+            let observer = observer.clone();
+            let scenario_id = *scenario_id;
+
+            tokio::spawn(async move {
+                while let Some(event) = rx.recv().await {
+                    if let Some(registry_observer) = &registry_observer {
+                        registry_observer.send(event.clone()).ok();
+                    }
+
+                    if let Some(observer) = &observer {
+                        observer.send((scenario_id, event)).ok();
+                    }
+                }
+            });
+
+            Some(tx)
+        } else {
+            None
+        };
+
+        // Scenarios cancelled while they wait for a slot are still run, to be recorded as such
+        let _permit = match &self.slots {
+            Some(slots) => execution_queue::acquire_slot(slots, &cancellation).await,
+            None => None,
+        };
+
+        let execution = self
+            .scenario_executor
+            .run_scenario_in_environment(
+                environment,
+                &scenario,
+                execution,
+                scenario_observer,
+                cancellation,
+            )
+            .await?;
+
+        Ok(SuiteScenarioResult::executed(
+            *scenario_id,
+            execution_id,
+            execution.status(),
+        ))
+    }
+}
@@ -1,21 +1,58 @@
+use std::time::Duration;
+
 use warp::hyper;
 
+use crate::data::{AssertionFailure, ImageStatus};
+
 #[derive(thiserror::Error, Debug)]
 pub enum DomainError {
     #[error("{0}")]
     Docker(#[from] bollard::errors::Error),
     #[error("{0}")]
     Data(#[from] crate::data::DataError),
-    #[error("{0}")]
-    SimulatorNotReady(String),
+    #[error("Simulator {simulator} failed to start: {message}")]
+    ContainerFailedToStart { simulator: String, message: String },
+    #[error("Simulator {simulator} runs version {actual} of its image instead of {expected}")]
+    SimulatorVersionMismatch {
+        simulator: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("Simulator {simulator} was not ready within {timeout:?}")]
+    SimulatorNotReady {
+        simulator: String,
+        timeout: Duration,
+    },
     #[error("Error {status}: {message}")]
     SimulatorCommandFailed {
         step: usize,
         message: String,
         status: hyper::StatusCode,
     },
+    #[error("Step {step} failed {} assertion(s)", failures.len())]
+    StepAssertionsFailed {
+        step: usize,
+        message: String,
+        status: hyper::StatusCode,
+        failures: Vec<AssertionFailure>,
+    },
+    #[error("Step {step} failed: {message}")]
+    StepVariablesFailed {
+        step: usize,
+        message: String,
+        status: hyper::StatusCode,
+    },
+    #[error("Step {step} timed out after {timeout:?}")]
+    SimulatorCommandTimedOut { step: usize, timeout: Duration },
     #[error("Simulator not found. Simulator ID: {0:#?}")]
     SimulatorNotFound(String),
+    #[error("Image build failed: {message}")]
+    ImageBuildFailed { message: String, log: Vec<String> },
     #[error("Image not found. Image ID: {0:#?}")]
     ImageNotFound(String),
+    #[error("Image {image_id} is not ready: {status:?}")]
+    ImageNotReady {
+        image_id: String,
+        status: ImageStatus,
+    },
 }
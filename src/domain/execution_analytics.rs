@@ -0,0 +1,504 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Local};
+use chrono_tz::Tz;
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, to_bson, Bson, Document};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{
+    deserialize_date, to_bson_date, Execution, ExecutionStatus, Repository, Scenario,
+};
+
+use super::error::DomainError;
+
+/// Periods the pass rates are computed over.
+#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Interval {
+    Hour,
+    #[default]
+    Day,
+    Month,
+}
+
+impl Interval {
+    fn unit(self) -> &'static str {
+        match self {
+            Interval::Hour => "hour",
+            Interval::Day => "day",
+            Interval::Month => "month",
+        }
+    }
+
+    /// Periods are named after the start of the period, the way RFC 3339 dates begin.
+    fn format(self) -> &'static str {
+        match self {
+            Interval::Hour => "%Y-%m-%dT%H",
+            Interval::Day => "%Y-%m-%d",
+            Interval::Month => "%Y-%m",
+        }
+    }
+}
+
+/// Matches the UTC offsets MongoDB buckets dates in, such as `+02:00`.
+fn offset_regex() -> &'static Regex {
+    static OFFSET_REGEX: OnceLock<Regex> = OnceLock::new();
+
+    OFFSET_REGEX.get_or_init(|| Regex::new(r"^[+-]\d{2}(:?\d{2})?$").expect("Invalid offset regex"))
+}
+
+/// Periods start at midnight in a given timezone, which the server cannot guess from the dates.
+/// MongoDB knows the same Olson timezones as `chrono_tz`, such as `Europe/Paris` or `GMT`.
+pub fn check_timezone(timezone: &str) -> Result<(), String> {
+    if offset_regex().is_match(timezone) || timezone.parse::<Tz>().is_ok() {
+        Ok(())
+    } else {
+        Err(format!(
+            "timezone {} must be an offset such as +02:00, or a name such as Europe/Paris",
+            timezone
+        ))
+    }
+}
+
+/// Which executions are analyzed, and how.
+#[derive(Debug, Clone)]
+pub struct AnalyticsOptions {
+    pub scenario_id: Option<ObjectId>,
+    pub environment_id: Option<ObjectId>,
+    pub from: Option<DateTime<Local>>,
+    pub to: Option<DateTime<Local>>,
+    pub interval: Interval,
+    /// Timezone the periods of pass rates start in.
+    pub timezone: String,
+    /// How many of the most failing steps are kept.
+    pub top: i64,
+    /// A single flip is usually a fix or a regression, so it takes more to be flaky.
+    pub min_flips: u32,
+}
+
+impl Default for AnalyticsOptions {
+    fn default() -> Self {
+        Self {
+            scenario_id: None,
+            environment_id: None,
+            from: None,
+            to: None,
+            interval: Interval::default(),
+            timezone: String::from("UTC"),
+            top: 10,
+            min_flips: 2,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PassRate {
+    #[serde(rename = "scenarioId")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scenario_id: Option<String>,
+    #[serde(rename = "environmentId")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    environment_id: Option<String>,
+    period: String,
+    runs: u32,
+    passed: u32,
+    #[serde(rename = "passRate")]
+    pass_rate: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FailingStep {
+    #[serde(rename = "scenarioId")]
+    scenario_id: String,
+    step: usize,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    failures: u32,
+    timeouts: u32,
+    #[serde(rename = "lastFailedAt")]
+    #[serde(deserialize_with = "deserialize_date")]
+    last_failed_at: DateTime<Local>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StepLatency {
+    #[serde(rename = "scenarioId")]
+    scenario_id: String,
+    step: usize,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    samples: u32,
+    #[serde(rename = "medianMs")]
+    median_ms: u64,
+    #[serde(rename = "p95Ms")]
+    p95_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScenarioStability {
+    #[serde(rename = "scenarioId")]
+    scenario_id: String,
+    #[serde(rename = "environmentId")]
+    environment_id: String,
+    runs: u32,
+    passed: u32,
+    /// Times the outcome changed from one run to the next.
+    flips: u32,
+    #[serde(default)]
+    flaky: bool,
+}
+
+/// Trends over the history of executions. Only passed and failed executions count towards pass
+/// rates and flakiness, as errored and cancelled ones say nothing about the scenario.
+#[derive(Debug, Serialize)]
+pub struct ExecutionAnalytics {
+    #[serde(rename = "passRateByScenario")]
+    pass_rate_by_scenario: Vec<PassRate>,
+    #[serde(rename = "passRateByEnvironment")]
+    pass_rate_by_environment: Vec<PassRate>,
+    #[serde(rename = "failingSteps")]
+    failing_steps: Vec<FailingStep>,
+    #[serde(rename = "stepLatencies")]
+    step_latencies: Vec<StepLatency>,
+    scenarios: Vec<ScenarioStability>,
+}
+
+impl ExecutionAnalytics {
+    pub async fn compute(
+        repository: &Repository,
+        options: &AnalyticsOptions,
+    ) -> Result<Self, DomainError> {
+        let scope = scope(options);
+
+        let mut analytics = Self {
+            pass_rate_by_scenario: repository
+                .aggregate::<Execution, _>(pass_rate_pipeline(&scope, options, "scenarioId"))
+                .await?,
+            pass_rate_by_environment: repository
+                .aggregate::<Execution, _>(pass_rate_pipeline(&scope, options, "environmentId"))
+                .await?,
+            failing_steps: repository
+                .aggregate::<Execution, _>(failing_steps_pipeline(&scope, options))
+                .await?,
+            step_latencies: repository
+                .aggregate::<Execution, _>(step_latencies_pipeline(&scope))
+                .await?,
+            scenarios: repository
+                .aggregate::<Execution, _>(stability_pipeline(&scope))
+                .await?,
+        };
+
+        for scenario in &mut analytics.scenarios {
+            scenario.flaky = scenario.flips >= options.min_flips;
+        }
+
+        analytics.name_steps(repository).await?;
+
+        Ok(analytics)
+    }
+
+    /// Steps are named after their command, unless their scenario was removed.
+    async fn name_steps(&mut self, repository: &Repository) -> Result<(), DomainError> {
+        let scenario_ids = self
+            .failing_steps
+            .iter()
+            .map(|step| &step.scenario_id)
+            .chain(self.step_latencies.iter().map(|step| &step.scenario_id))
+            .filter_map(|scenario_id| ObjectId::parse_str(scenario_id).ok())
+            .collect::<Vec<_>>();
+
+        let scenarios = repository
+            .find::<Scenario>(doc! { "_id": { "$in": scenario_ids } })
+            .await?
+            .into_iter()
+            .filter_map(|scenario| Some((scenario.id()?.to_string(), scenario)))
+            .collect::<HashMap<_, _>>();
+
+        let step_name = |scenario_id: &str, step: usize| {
+            scenarios
+                .get(scenario_id)
+                .and_then(|scenario| scenario.steps().get(step.checked_sub(1)?))
+                .map(|step| step.command.name.clone())
+        };
+
+        for step in &mut self.failing_steps {
+            step.name = step_name(&step.scenario_id, step.step);
+        }
+
+        for step in &mut self.step_latencies {
+            step.name = step_name(&step.scenario_id, step.step);
+        }
+
+        Ok(())
+    }
+}
+
+fn scope(options: &AnalyticsOptions) -> Document {
+    let mut scope = doc! {};
+
+    if let Some(scenario_id) = options.scenario_id {
+        scope.insert("scenarioId", scenario_id);
+    }
+
+    if let Some(environment_id) = options.environment_id {
+        scope.insert("environmentId", environment_id);
+    }
+
+    let mut timestamp = doc! {};
+
+    if let Some(from) = options.from {
+        timestamp.insert("$gte", to_bson_date(&from));
+    }
+
+    if let Some(to) = options.to {
+        timestamp.insert("$lte", to_bson_date(&to));
+    }
+
+    if !timestamp.is_empty() {
+        scope.insert("timestamp", timestamp);
+    }
+
+    scope
+}
+
+/// Executions that ran to the end, whether they passed or failed.
+fn concluded(scope: &Document) -> Document {
+    let mut concluded = scope.clone();
+    concluded.insert(
+        "status",
+        doc! { "$in": to_bson(&[ExecutionStatus::Passed, ExecutionStatus::Failed]).unwrap() },
+    );
+
+    concluded
+}
+
+fn count_of(status: ExecutionStatus) -> Document {
+    doc! { "$sum": { "$cond": [{ "$eq": ["$status", to_bson(&status).unwrap()] }, 1, 0] } }
+}
+
+fn pass_rate_pipeline(scope: &Document, options: &AnalyticsOptions, key: &str) -> Vec<Document> {
+    vec![
+        doc! { "$match": concluded(scope) },
+        doc! {
+            "$group": {
+                "_id": {
+                    "key": format!("${}", key),
+                    "period": {
+                        "$dateTrunc": {
+                            "date": "$timestamp",
+                            "unit": options.interval.unit(),
+                            "timezone": &options.timezone,
+                        }
+                    },
+                },
+                "runs": { "$sum": 1 },
+                "passed": count_of(ExecutionStatus::Passed),
+            }
+        },
+        doc! { "$sort": { "_id.period": 1, "_id.key": 1 } },
+        doc! {
+            "$project": {
+                "_id": 0,
+                key: { "$toString": "$_id.key" },
+                "period": {
+                    "$dateToString": {
+                        "date": "$_id.period",
+                        "format": options.interval.format(),
+                        "timezone": &options.timezone,
+                    }
+                },
+                "runs": 1,
+                "passed": 1,
+                "passRate": { "$divide": ["$passed", "$runs"] },
+            }
+        },
+    ]
+}
+
+/// Events of the steps of the executions in scope, one document each.
+fn step_events(scope: &Document, types: &[&str]) -> [Document; 3] {
+    [
+        doc! { "$match": scope.clone() },
+        doc! { "$unwind": "$events" },
+        doc! { "$match": { "events.type": { "$in": types } } },
+    ]
+}
+
+fn failing_steps_pipeline(scope: &Document, options: &AnalyticsOptions) -> Vec<Document> {
+    let mut pipeline = step_events(scope, &["StepFailed", "StepTimedOut"]).to_vec();
+
+    pipeline.extend([
+        doc! {
+            "$group": {
+                "_id": { "scenarioId": "$scenarioId", "step": "$events.step" },
+                "failures": { "$sum": 1 },
+                "timeouts": {
+                    "$sum": { "$cond": [{ "$eq": ["$events.type", "StepTimedOut"] }, 1, 0] }
+                },
+                "lastFailedAt": { "$max": "$timestamp" },
+            }
+        },
+        doc! { "$sort": { "failures": -1, "_id.scenarioId": 1, "_id.step": 1 } },
+        doc! { "$limit": options.top },
+        doc! {
+            "$project": {
+                "_id": 0,
+                "scenarioId": { "$toString": "$_id.scenarioId" },
+                "step": "$_id.step",
+                "failures": 1,
+                "timeouts": 1,
+                "lastFailedAt": 1,
+            }
+        },
+    ]);
+
+    pipeline
+}
+
+/// Nearest-rank percentile of an array sorted in ascending order.
+fn percentile(array: &str, rank: f64) -> Bson {
+    Bson::Document(doc! {
+        "$arrayElemAt": [
+            array,
+            {
+                "$toInt": {
+                    "$subtract": [{ "$ceil": { "$multiply": [rank, { "$size": array }] } }, 1]
+                }
+            },
+        ]
+    })
+}
+
+fn step_latencies_pipeline(scope: &Document) -> Vec<Document> {
+    let mut pipeline = step_events(scope, &["StepPassed", "StepFailed", "StepTimedOut"]).to_vec();
+
+    pipeline.extend([
+        // Steps run before their timing was recorded say nothing about latency
+        doc! { "$match": { "events.timing.durationMs": { "$exists": true } } },
+        doc! { "$sort": { "events.timing.durationMs": 1 } },
+        doc! {
+            "$group": {
+                "_id": { "scenarioId": "$scenarioId", "step": "$events.step" },
+                "durations": { "$push": "$events.timing.durationMs" },
+            }
+        },
+        doc! {
+            "$project": {
+                "_id": 0,
+                "scenarioId": { "$toString": "$_id.scenarioId" },
+                "step": "$_id.step",
+                "samples": { "$size": "$durations" },
+                "medianMs": percentile("$durations", 0.5),
+                "p95Ms": percentile("$durations", 0.95),
+            }
+        },
+        doc! { "$sort": { "scenarioId": 1, "step": 1 } },
+    ]);
+
+    pipeline
+}
+
+fn stability_pipeline(scope: &Document) -> Vec<Document> {
+    let passed = to_bson(&ExecutionStatus::Passed).unwrap();
+
+    vec![
+        doc! { "$match": concluded(scope) },
+        doc! { "$sort": { "timestamp": 1 } },
+        doc! {
+            "$group": {
+                "_id": { "scenarioId": "$scenarioId", "environmentId": "$environmentId" },
+                "statuses": { "$push": "$status" },
+            }
+        },
+        doc! {
+            "$project": {
+                "_id": 0,
+                "scenarioId": { "$toString": "$_id.scenarioId" },
+                "environmentId": { "$toString": "$_id.environmentId" },
+                "runs": { "$size": "$statuses" },
+                "passed": {
+                    "$size": {
+                        "$filter": { "input": "$statuses", "cond": { "$eq": ["$$this", passed] } }
+                    }
+                },
+                "outcomes": {
+                    "$reduce": {
+                        "input": "$statuses",
+                        "initialValue": { "previous": Bson::Null, "flips": 0 },
+                        "in": {
+                            "previous": "$$this",
+                            "flips": {
+                                "$add": [
+                                    "$$value.flips",
+                                    {
+                                        "$cond": [
+                                            {
+                                                "$and": [
+                                                    { "$ne": ["$$value.previous", Bson::Null] },
+                                                    { "$ne": ["$$value.previous", "$$this"] },
+                                                ]
+                                            },
+                                            1,
+                                            0,
+                                        ]
+                                    },
+                                ]
+                            },
+                        },
+                    }
+                },
+            }
+        },
+        doc! {
+            "$project": {
+                "scenarioId": 1,
+                "environmentId": 1,
+                "runs": 1,
+                "passed": 1,
+                "flips": "$outcomes.flips",
+            }
+        },
+        doc! { "$sort": { "flips": -1, "scenarioId": 1, "environmentId": 1 } },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_the_timezones_mongodb_knows() {
+        for timezone in [
+            "UTC",
+            "GMT",
+            "EST",
+            "+02:00",
+            "-0530",
+            "Europe/Paris",
+            "America/Argentina/Buenos_Aires",
+        ] {
+            assert_eq!(check_timezone(timezone), Ok(()), "{}", timezone);
+        }
+    }
+
+    #[test]
+    fn rejects_other_timezones() {
+        for timezone in [
+            "",
+            "Paris",
+            "Foo/Bar",
+            "+2",
+            "Europe/Paris; drop",
+            "$timestamp",
+        ] {
+            assert!(check_timezone(timezone).is_err(), "{}", timezone);
+        }
+    }
+}
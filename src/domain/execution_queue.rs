@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use bollard::Docker;
+use chrono::Local;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+use crate::data::{Environment, Execution, Repository, Scenario, Suite, SuiteExecution};
+use crate::domain::{
+    DockerScenarioExecutor, DockerSuiteExecutor, ExecutionRegistry, ExecutionSettings,
+};
+
+use super::error::DomainError;
+
+/// Runs executions in the background, no more than the configured number at a time.
+#[derive(Clone)]
+pub struct ExecutionQueue {
+    docker: Arc<Docker>,
+    repository: Repository,
+    settings: ExecutionSettings,
+    registry: ExecutionRegistry,
+    slots: Arc<Semaphore>,
+}
+
+impl ExecutionQueue {
+    pub fn new(docker: Arc<Docker>, repository: Repository, settings: ExecutionSettings) -> Self {
+        Self {
+            docker,
+            repository,
+            settings,
+            registry: ExecutionRegistry::default(),
+            slots: Arc::new(Semaphore::new(settings.max_concurrent_executions)),
+        }
+    }
+
+    pub fn registry(&self) -> &ExecutionRegistry {
+        &self.registry
+    }
+
+    /// Queues an execution that was already saved. It is live in the registry as soon as this
+    /// returns.
+    pub fn queue(&self, environment: Environment, scenario: Scenario, execution: Execution) {
+        let execution_id = execution
+            .id()
+            .expect("Executions are saved before being queued")
+            .to_owned();
+
+        let (observer, cancellation) = self
+            .registry
+            .register(execution_id, CancellationToken::new());
+
+        let executor = DockerScenarioExecutor::new(
+            self.docker.clone(),
+            self.repository.clone(),
+            self.settings,
+        );
+        let slots = self.slots.clone();
+
+        tokio::spawn(async move {
+            let _permit = acquire_slot(&slots, &cancellation).await;
+
+            if let Err(error) = executor
+                .run_scenario_in_environment(
+                    &environment,
+                    &scenario,
+                    execution,
+                    Some(observer),
+                    cancellation,
+                )
+                .await
+            {
+                warn!("Could not run execution {}: {:?}", execution_id, error);
+            }
+        });
+    }
+
+    /// Saves an execution of the suite and runs its scenarios in the background, each of them
+    /// being queued like any other execution.
+    pub async fn queue_suite(
+        &self,
+        environment: Environment,
+        suite: Suite,
+    ) -> Result<SuiteExecution, DomainError> {
+        let suite_execution = self
+            .repository
+            .create(SuiteExecution::new(
+                *suite.id().unwrap(),
+                *environment.id().unwrap(),
+                Local::now(),
+            ))
+            .await?;
+
+        let executor =
+            DockerSuiteExecutor::new(self.docker.clone(), self.repository.clone(), self.settings)
+                .queued(self.registry.clone(), self.slots.clone());
+        let queued_suite_execution = suite_execution.clone();
+
+        tokio::spawn(async move {
+            if let Err(error) = executor
+                .run_suite_in_environment(
+                    &environment,
+                    &suite,
+                    queued_suite_execution,
+                    None,
+                    CancellationToken::new(),
+                )
+                .await
+            {
+                warn!("Could not run suite {:?}: {:?}", suite.id(), error);
+            }
+        });
+
+        Ok(suite_execution)
+    }
+}
+
+/// Waits for an execution slot. Executions cancelled while they wait get no slot, and are left to
+/// end right away.
+pub(super) async fn acquire_slot<'a>(
+    slots: &'a Semaphore,
+    cancellation: &CancellationToken,
+) -> Option<SemaphorePermit<'a>> {
+    tokio::select! {
+        permit = slots.acquire() => Some(permit.expect("The execution slots are never closed")),
+        _ = cancellation.cancelled() => None,
+    }
+}
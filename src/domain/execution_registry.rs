@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use mongodb::bson::oid::ObjectId;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio_util::sync::CancellationToken;
+
+use crate::data::ScenarioPlayingEvent;
+
+struct LiveExecution {
+    events: Vec<ScenarioPlayingEvent>,
+    subscribers: Vec<UnboundedSender<ScenarioPlayingEvent>>,
+    cancellation: CancellationToken,
+}
+
+/// Keeps track of the executions that are queued or running, so that clients can follow or cancel
+/// them independently of the request that started them.
+#[derive(Clone, Default)]
+pub struct ExecutionRegistry {
+    executions: Arc<Mutex<HashMap<ObjectId, LiveExecution>>>,
+}
+
+impl ExecutionRegistry {
+    /// Registers an execution before it starts, along with the token cancelling it. The returned
+    /// sender is given to the executor, and the execution is forgotten once the sender is dropped.
+    pub fn register(
+        &self,
+        execution_id: ObjectId,
+        cancellation: CancellationToken,
+    ) -> (UnboundedSender<ScenarioPlayingEvent>, CancellationToken) {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        self.executions.lock().unwrap().insert(
+            execution_id,
+            LiveExecution {
+                events: Vec::new(),
+                subscribers: Vec::new(),
+                cancellation: cancellation.clone(),
+            },
+        );
+
+        let registry = self.clone();
+
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                registry.publish(&execution_id, event);
+            }
+
+            // Dropping the subscribers ends their streams
+            registry.executions.lock().unwrap().remove(&execution_id);
+        });
+
+        (tx, cancellation)
+    }
+
+    fn publish(&self, execution_id: &ObjectId, event: ScenarioPlayingEvent) {
+        let mut executions = self.executions.lock().unwrap();
+
+        if let Some(live_execution) = executions.get_mut(execution_id) {
+            live_execution
+                .subscribers
+                .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+            live_execution.events.push(event);
+        }
+    }
+
+    /// Returns the events of a live execution, starting with the ones that already happened, along
+    /// with the token cancelling it. Returns `None` when the execution is not live.
+    pub fn subscribe(
+        &self,
+        execution_id: &ObjectId,
+    ) -> Option<(UnboundedReceiver<ScenarioPlayingEvent>, CancellationToken)> {
+        let mut executions = self.executions.lock().unwrap();
+        let live_execution = executions.get_mut(execution_id)?;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        for event in &live_execution.events {
+            tx.send(event.clone()).ok();
+        }
+
+        live_execution.subscribers.push(tx);
+
+        Some((rx, live_execution.cancellation.clone()))
+    }
+
+    /// Returns `false` when the execution is not live.
+    pub fn cancel(&self, execution_id: &ObjectId) -> bool {
+        match self.executions.lock().unwrap().get(execution_id) {
+            Some(live_execution) => {
+                live_execution.cancellation.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
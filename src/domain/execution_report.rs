@@ -0,0 +1,701 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::data::{
+    AssertionFailure, Environment, Execution, ExecutionStatus, LogMessage, Scenario,
+    ScenarioPlayingEvent,
+};
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum StepStatus {
+    Passed,
+    Failed,
+    TimedOut,
+    Skipped,
+    /// The step was running, or about to, when the execution was cancelled.
+    Cancelled,
+    /// The step was running, or about to, when the execution errored.
+    Errored,
+}
+
+/// Outcome of one step of an execution, the way test reports show it.
+#[derive(Debug, Serialize)]
+pub struct StepReport {
+    step: usize,
+    name: String,
+    path: String,
+    status: StepStatus,
+    #[serde(rename = "httpStatus")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    http_status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    failures: Vec<AssertionFailure>,
+    #[serde(rename = "durationMs")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u64>,
+    logs: Vec<LogMessage>,
+}
+
+/// An execution summarized step by step, which can be exported for CI dashboards.
+#[derive(Debug, Serialize)]
+pub struct ExecutionReport {
+    #[serde(rename = "executionId")]
+    execution_id: String,
+    scenario: String,
+    environment: String,
+    status: ExecutionStatus,
+    timestamp: DateTime<Local>,
+    #[serde(rename = "durationMs")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u64>,
+    /// Why the execution errored outside of its steps, such as when its simulators could not be
+    /// set up.
+    #[serde(rename = "setupError")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    setup_error: Option<String>,
+    /// Logs of the simulators written before the first step.
+    #[serde(rename = "setupLogs")]
+    setup_logs: Vec<LogMessage>,
+    steps: Vec<StepReport>,
+}
+
+impl ExecutionReport {
+    /// Steps that were never reached are skipped. Logs are given to the step that was running when
+    /// they were written. Steps are the ones the execution ran, or the current ones of the
+    /// scenario for executions that did not keep them.
+    pub fn new(
+        execution: &Execution,
+        scenario: Option<&Scenario>,
+        environment: Option<&Environment>,
+    ) -> Self {
+        let mut steps = execution
+            .steps()
+            .or(scenario.map(Scenario::steps))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+            .map(|(index, step)| StepReport {
+                step: index + 1,
+                name: step.command.name.clone(),
+                path: step.command.path.clone(),
+                status: StepStatus::Skipped,
+                http_status: None,
+                message: None,
+                failures: Vec::new(),
+                duration_ms: None,
+                logs: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut setup_error = None;
+        let mut setup_logs = Vec::new();
+        let mut started = false;
+        let mut current_step = 1;
+
+        for event in execution.events() {
+            match event {
+                ScenarioPlayingEvent::ScenarioStarting => started = true,
+                ScenarioPlayingEvent::StepPassed {
+                    step,
+                    message,
+                    timing,
+                } => {
+                    if let Some(step_report) = step_report(&mut steps, *step) {
+                        step_report.status = StepStatus::Passed;
+                        step_report.message = Some(message.clone());
+                        step_report.duration_ms = timing.map(|timing| timing.duration_ms);
+                    }
+
+                    current_step = step + 1;
+                }
+                ScenarioPlayingEvent::StepFailed {
+                    step,
+                    message,
+                    status,
+                    failures,
+                    timing,
+                } => {
+                    if let Some(step_report) = step_report(&mut steps, *step) {
+                        step_report.status = StepStatus::Failed;
+                        step_report.message = Some(message.clone());
+                        step_report.http_status = Some(*status);
+                        step_report.failures = failures.clone();
+                        step_report.duration_ms = timing.map(|timing| timing.duration_ms);
+                    }
+                }
+                ScenarioPlayingEvent::StepTimedOut {
+                    step,
+                    timeout_ms,
+                    timing,
+                } => {
+                    if let Some(step_report) = step_report(&mut steps, *step) {
+                        step_report.status = StepStatus::TimedOut;
+                        step_report.message = Some(format!("Timed out after {}ms", timeout_ms));
+                        step_report.duration_ms = timing.map(|timing| timing.duration_ms);
+                    }
+                }
+                ScenarioPlayingEvent::LogReceived { log_message } => {
+                    let last_step = steps.len();
+
+                    match steps.get_mut(current_step.min(last_step).saturating_sub(1)) {
+                        Some(step_report) if started => step_report.logs.push(log_message.clone()),
+                        _ => setup_logs.push(log_message.clone()),
+                    }
+                }
+                ScenarioPlayingEvent::VariableCaptured { .. }
+                | ScenarioPlayingEvent::ScenarioCancelled => {}
+                setup_failure => {
+                    setup_error = Some(serde_json::to_string(setup_failure).unwrap_or_default())
+                }
+            }
+        }
+
+        // Executions that did not run to the end show where they stopped, as their remaining steps
+        // are only skipped
+        let interrupted_step = step_report(&mut steps, current_step)
+            .filter(|step_report| step_report.status == StepStatus::Skipped);
+
+        match (execution.status(), interrupted_step) {
+            (ExecutionStatus::Cancelled, Some(step_report)) => {
+                step_report.status = StepStatus::Cancelled;
+                step_report.message = Some(String::from(if started {
+                    "Cancelled while the step was running"
+                } else {
+                    "Cancelled before the scenario started"
+                }));
+            }
+            (ExecutionStatus::Errored, Some(step_report)) if started && setup_error.is_none() => {
+                step_report.status = StepStatus::Errored;
+                step_report.message = Some(String::from("Errored while the step was running"));
+            }
+            (ExecutionStatus::Errored, _) if setup_error.is_none() => {
+                setup_error = Some(String::from(if started {
+                    "Errored after the steps ran"
+                } else {
+                    "Errored before the scenario started"
+                }));
+            }
+            _ => {}
+        }
+
+        Self {
+            execution_id: execution.id().map(ToString::to_string).unwrap_or_default(),
+            scenario: scenario
+                .map(|scenario| scenario.name().to_owned())
+                .unwrap_or_else(|| execution.scenario_id().to_string()),
+            environment: environment
+                .map(|environment| environment.name().to_owned())
+                .unwrap_or_else(|| execution.environment_id().to_string()),
+            status: execution.status(),
+            timestamp: execution.timestamp(),
+            duration_ms: execution.duration_ms(),
+            setup_error,
+            setup_logs,
+            steps,
+        }
+    }
+
+    /// Each step is a test case. Setup failures are reported as an error of an extra test case, as
+    /// no step could run.
+    pub fn to_junit(&self) -> String {
+        let count = |status: StepStatus| {
+            self.steps
+                .iter()
+                .filter(|step| step.status == status)
+                .count()
+        };
+
+        let failures =
+            count(StepStatus::Failed) + count(StepStatus::TimedOut) + count(StepStatus::Cancelled);
+        let skipped = count(StepStatus::Skipped);
+        let setup_errors = usize::from(self.setup_error.is_some());
+        let errors = count(StepStatus::Errored) + setup_errors;
+        let tests = self.steps.len() + setup_errors;
+        let time = seconds(self.duration_ms);
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+
+        writeln!(
+            xml,
+            "<testsuites name=\"meta\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{}\">",
+            tests, failures, errors, skipped, time
+        )
+        .unwrap();
+        writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{}\" timestamp=\"{}\">",
+            escape(&self.scenario),
+            tests,
+            failures,
+            errors,
+            skipped,
+            time,
+            self.timestamp.to_rfc3339()
+        )
+        .unwrap();
+        writeln!(xml, "    <properties>").unwrap();
+        for (name, value) in [
+            ("executionId", &self.execution_id),
+            ("environment", &self.environment),
+        ] {
+            writeln!(
+                xml,
+                "      <property name=\"{}\" value=\"{}\"/>",
+                name,
+                escape(value)
+            )
+            .unwrap();
+        }
+        writeln!(xml, "    </properties>").unwrap();
+
+        if let Some(setup_error) = &self.setup_error {
+            writeln!(
+                xml,
+                "    <testcase name=\"Setup\" classname=\"{}\">",
+                escape(&self.scenario)
+            )
+            .unwrap();
+            writeln!(
+                xml,
+                "      <error message=\"{}\" type=\"setup\"/>",
+                escape(setup_error)
+            )
+            .unwrap();
+            writeln!(xml, "    </testcase>").unwrap();
+        }
+
+        for step in &self.steps {
+            write!(
+                xml,
+                "    <testcase name=\"Step {}: {}\" classname=\"{}\"",
+                step.step,
+                escape(&step.name),
+                escape(&self.scenario)
+            )
+            .unwrap();
+            if let Some(duration_ms) = step.duration_ms {
+                write!(xml, " time=\"{}\"", seconds(Some(duration_ms))).unwrap();
+            }
+            writeln!(xml, ">").unwrap();
+
+            let message = step.message.as_deref().unwrap_or_default();
+
+            match step.status {
+                StepStatus::Passed => {}
+                StepStatus::Failed => {
+                    let failure_type = step
+                        .http_status
+                        .map(|status| format!("HTTP {}", status))
+                        .unwrap_or_else(|| String::from("failure"));
+
+                    writeln!(
+                        xml,
+                        "      <failure message=\"{}\" type=\"{}\">{}</failure>",
+                        escape(&failure_summary(step)),
+                        failure_type,
+                        escape(message)
+                    )
+                    .unwrap();
+                }
+                StepStatus::TimedOut => writeln!(
+                    xml,
+                    "      <failure message=\"{}\" type=\"timeout\"/>",
+                    escape(message)
+                )
+                .unwrap(),
+                StepStatus::Skipped => writeln!(xml, "      <skipped/>").unwrap(),
+                StepStatus::Cancelled => writeln!(
+                    xml,
+                    "      <failure message=\"{}\" type=\"cancelled\"/>",
+                    escape(message)
+                )
+                .unwrap(),
+                StepStatus::Errored => writeln!(
+                    xml,
+                    "      <error message=\"{}\" type=\"error\"/>",
+                    escape(message)
+                )
+                .unwrap(),
+            }
+
+            write_logs(&mut xml, &step.logs, "      ");
+            writeln!(xml, "    </testcase>").unwrap();
+        }
+
+        write_logs(&mut xml, &self.setup_logs, "    ");
+        writeln!(xml, "  </testsuite>").unwrap();
+        writeln!(xml, "</testsuites>").unwrap();
+
+        xml
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+
+        writeln!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>",
+            escape(&self.scenario)
+        )
+        .unwrap();
+        writeln!(
+            html,
+            "<style>\
+            body {{ font-family: sans-serif; }} \
+            table {{ border-collapse: collapse; }} \
+            td, th {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top; }} \
+            .Passed {{ color: #2e7d32; }} .Failed, .TimedOut, .Errored {{ color: #c62828; }} \
+            .Skipped, .Cancelled {{ color: #757575; }} \
+            pre {{ margin: 0; white-space: pre-wrap; }}\
+            </style>\n</head>\n<body>"
+        )
+        .unwrap();
+        writeln!(
+            html,
+            "<h1>{}</h1>\n<p>Environment {} &middot; {} &middot; <span class=\"{:?}\">{:?}</span>{}</p>",
+            escape(&self.scenario),
+            escape(&self.environment),
+            self.timestamp.to_rfc3339(),
+            self.status,
+            self.status,
+            self.duration_ms
+                .map(|duration_ms| format!(" in {}ms", duration_ms))
+                .unwrap_or_default()
+        )
+        .unwrap();
+
+        if let Some(setup_error) = &self.setup_error {
+            writeln!(
+                html,
+                "<p class=\"Errored\">Error: {}</p>",
+                escape(setup_error)
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            html,
+            "<table>\n<tr><th>Step</th><th>Command</th><th>Status</th><th>HTTP</th><th>Message</th><th>Logs</th></tr>"
+        )
+        .unwrap();
+
+        for step in &self.steps {
+            let message = failure_summary(step);
+            let logs = step
+                .logs
+                .iter()
+                .map(|log| format!("{} | {}", log.simulator_name, log.message))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            writeln!(
+                html,
+                "<tr><td>{}</td><td>{} <code>{}</code></td><td class=\"{:?}\">{:?}</td><td>{}</td><td><pre>{}</pre></td><td><pre>{}</pre></td></tr>",
+                step.step,
+                escape(&step.name),
+                escape(&step.path),
+                step.status,
+                step.status,
+                step.http_status.map(|status| status.to_string()).unwrap_or_default(),
+                escape(&message),
+                escape(&logs)
+            )
+            .unwrap();
+        }
+
+        writeln!(html, "</table>\n</body>\n</html>").unwrap();
+
+        html
+    }
+}
+
+fn step_report(steps: &mut [StepReport], step: usize) -> Option<&mut StepReport> {
+    steps.get_mut(step.checked_sub(1)?)
+}
+
+fn failure_summary(step: &StepReport) -> String {
+    if step.failures.is_empty() {
+        return step.message.clone().unwrap_or_default();
+    }
+
+    step.failures
+        .iter()
+        .map(|failure| {
+            format!(
+                "{}: expected {}, got {}",
+                failure.message, failure.expected, failure.actual
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Error logs go to the standard error of the test case, the others to its standard output.
+fn write_logs(xml: &mut String, logs: &[LogMessage], indentation: &str) {
+    for (tag, is_error) in [("system-out", false), ("system-err", true)] {
+        let lines = logs
+            .iter()
+            .filter(|log| log.is_error == is_error)
+            .map(|log| format!("{} | {}", log.simulator_name, log.message))
+            .collect::<Vec<_>>();
+
+        if !lines.is_empty() {
+            writeln!(
+                xml,
+                "{}<{}>{}</{}>",
+                indentation,
+                tag,
+                escape(&lines.join("\n")),
+                tag
+            )
+            .unwrap();
+        }
+    }
+}
+
+fn seconds(duration_ms: Option<u64>) -> String {
+    format!("{:.3}", duration_ms.unwrap_or_default() as f64 / 1000.0)
+}
+
+/// Escapes text for XML and HTML, dropping the control characters XML does not allow.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(character),
+            character if character.is_control() => {}
+            character => escaped.push(character),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::oid::ObjectId;
+    use serde_json::json;
+
+    use crate::data::{Command, ImageVersion, Step};
+
+    use super::*;
+
+    fn scenario() -> Scenario {
+        let step = |path: &str| Step {
+            image_id: ObjectId::new(),
+            image_version: ImageVersion::Pinned,
+            command: Command {
+                name: path.to_uppercase(),
+                description: String::new(),
+                path: String::from(path),
+                timeout_ms: None,
+                arguments_schema: None,
+                response_schema: None,
+            },
+            arguments: json!({}),
+            timeout_ms: None,
+            expectations: Vec::new(),
+            captures: Vec::new(),
+        };
+
+        Scenario::new(
+            String::from("Greetings"),
+            String::new(),
+            vec![step("hello"), step("bye")],
+        )
+    }
+
+    fn execution() -> Execution {
+        Execution::new(
+            ObjectId::new(),
+            ObjectId::new(),
+            scenario().steps().clone(),
+            Local::now(),
+        )
+        .start(Local::now())
+    }
+
+    fn passed(step: usize) -> ScenarioPlayingEvent {
+        ScenarioPlayingEvent::StepPassed {
+            step,
+            message: String::from("OK"),
+            timing: None,
+        }
+    }
+
+    fn report(execution: Execution) -> ExecutionReport {
+        ExecutionReport::new(&execution, Some(&scenario()), None)
+    }
+
+    fn statuses(report: &ExecutionReport) -> Vec<StepStatus> {
+        report.steps.iter().map(|step| step.status).collect()
+    }
+
+    #[test]
+    fn steps_are_the_ones_the_execution_ran() {
+        let execution = Execution::new(
+            ObjectId::new(),
+            ObjectId::new(),
+            scenario().steps()[..1].to_vec(),
+            Local::now(),
+        );
+
+        let report = report(execution);
+
+        assert_eq!(report.steps.len(), 1);
+        assert_eq!(report.steps[0].path, "hello");
+    }
+
+    #[test]
+    fn executions_without_steps_have_the_ones_of_their_scenario() {
+        let execution = serde_json::from_value::<Execution>(json!({
+            "scenarioId": ObjectId::new().to_hex(),
+            "environmentId": ObjectId::new().to_hex(),
+            "timestamp": "2024-03-01T10:00:00+01:00",
+            "status": "Passed",
+        }))
+        .unwrap();
+
+        let report = report(execution);
+
+        assert_eq!(
+            statuses(&report),
+            [StepStatus::Skipped, StepStatus::Skipped]
+        );
+        assert_eq!(report.steps[1].path, "bye");
+    }
+
+    #[test]
+    fn passed_executions_have_no_failures() {
+        let report = report(execution().finish(
+            vec![ScenarioPlayingEvent::ScenarioStarting, passed(1), passed(2)],
+            Local::now(),
+        ));
+        let junit = report.to_junit();
+
+        assert_eq!(report.status, ExecutionStatus::Passed);
+        assert_eq!(statuses(&report), [StepStatus::Passed, StepStatus::Passed]);
+        assert!(junit.contains("tests=\"2\" failures=\"0\" errors=\"0\" skipped=\"0\""));
+    }
+
+    #[test]
+    fn failed_steps_are_failures() {
+        let report = report(execution().finish(
+            vec![
+                ScenarioPlayingEvent::ScenarioStarting,
+                ScenarioPlayingEvent::StepFailed {
+                    step: 1,
+                    message: String::from("Nope"),
+                    status: 500,
+                    failures: Vec::new(),
+                    timing: None,
+                },
+            ],
+            Local::now(),
+        ));
+        let junit = report.to_junit();
+
+        assert_eq!(report.status, ExecutionStatus::Failed);
+        assert_eq!(statuses(&report), [StepStatus::Failed, StepStatus::Skipped]);
+        assert!(junit.contains("failures=\"1\" errors=\"0\" skipped=\"1\""));
+        assert!(junit.contains("<failure message=\"Nope\" type=\"HTTP 500\">"));
+    }
+
+    #[test]
+    fn cancelled_executions_fail_the_interrupted_step() {
+        let report = report(execution().finish(
+            vec![
+                ScenarioPlayingEvent::ScenarioStarting,
+                passed(1),
+                ScenarioPlayingEvent::ScenarioCancelled,
+            ],
+            Local::now(),
+        ));
+        let junit = report.to_junit();
+
+        assert_eq!(report.status, ExecutionStatus::Cancelled);
+        assert_eq!(
+            statuses(&report),
+            [StepStatus::Passed, StepStatus::Cancelled]
+        );
+        assert!(junit.contains("failures=\"1\" errors=\"0\" skipped=\"0\""));
+        assert!(junit.contains("type=\"cancelled\""));
+        assert!(report
+            .to_html()
+            .contains("<td class=\"Cancelled\">Cancelled</td>"));
+    }
+
+    #[test]
+    fn executions_cancelled_during_setup_fail_the_first_step() {
+        let report =
+            report(execution().finish(vec![ScenarioPlayingEvent::ScenarioCancelled], Local::now()));
+
+        assert_eq!(
+            statuses(&report),
+            [StepStatus::Cancelled, StepStatus::Skipped]
+        );
+        assert!(report
+            .to_junit()
+            .contains("failures=\"1\" errors=\"0\" skipped=\"1\""));
+    }
+
+    #[test]
+    fn errored_executions_error_the_interrupted_step() {
+        let report = report(execution().abort(
+            vec![ScenarioPlayingEvent::ScenarioStarting, passed(1)],
+            Local::now(),
+        ));
+        let junit = report.to_junit();
+
+        assert_eq!(report.status, ExecutionStatus::Errored);
+        assert_eq!(statuses(&report), [StepStatus::Passed, StepStatus::Errored]);
+        assert!(junit.contains("failures=\"0\" errors=\"1\" skipped=\"0\""));
+        assert!(junit.contains("<error message=\"Errored while the step was running\""));
+        assert_eq!(
+            serde_json::to_value(&report).unwrap()["steps"][1]["status"],
+            json!("Errored")
+        );
+    }
+
+    #[test]
+    fn setup_failures_are_errors_of_an_extra_test_case() {
+        let report = report(execution().abort(
+            vec![ScenarioPlayingEvent::SimulatorNotReady {
+                simulator: String::from("greeter"),
+                timeout_ms: 1000,
+            }],
+            Local::now(),
+        ));
+        let junit = report.to_junit();
+
+        assert_eq!(
+            statuses(&report),
+            [StepStatus::Skipped, StepStatus::Skipped]
+        );
+        assert!(junit.contains("tests=\"3\" failures=\"0\" errors=\"1\" skipped=\"2\""));
+        assert!(junit.contains("<testcase name=\"Setup\""));
+        assert!(report.setup_error.unwrap().contains("SimulatorNotReady"));
+    }
+
+    #[test]
+    fn executions_errored_without_a_cause_are_still_errors() {
+        let report = report(execution().abort(Vec::new(), Local::now()));
+
+        assert_eq!(
+            report.setup_error.as_deref(),
+            Some("Errored before the scenario started")
+        );
+        assert!(report.to_junit().contains("errors=\"1\""));
+    }
+}
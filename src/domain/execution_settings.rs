@@ -0,0 +1,18 @@
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExecutionSettings {
+    pub command_timeout: Duration,
+    pub readiness_timeout: Duration,
+    pub max_concurrent_executions: usize,
+}
+
+impl Default for ExecutionSettings {
+    fn default() -> Self {
+        Self {
+            command_timeout: Duration::from_secs(30),
+            readiness_timeout: Duration::from_secs(30),
+            max_concurrent_executions: 4,
+        }
+    }
+}
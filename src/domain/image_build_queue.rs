@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use bollard::Docker;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::Semaphore;
+use tracing::{info, warn};
+
+use crate::data::{Image, ImageBuildEvent, ImageDTO, Repository};
+
+use super::error::DomainError;
+use super::{DockerImage, ImageSource};
+
+/// Builds uploaded images in the background, no more than the configured number at a time.
+#[derive(Clone)]
+pub struct ImageBuildQueue {
+    docker: Arc<Docker>,
+    repository: Repository,
+    slots: Arc<Semaphore>,
+}
+
+impl ImageBuildQueue {
+    pub fn new(docker: Arc<Docker>, repository: Repository, max_concurrent_builds: usize) -> Self {
+        Self {
+            docker,
+            repository,
+            slots: Arc::new(Semaphore::new(max_concurrent_builds)),
+        }
+    }
+
+    /// Queues the creation of an image that was already saved as building. The image is updated
+    /// once the build ends, and the observer receives the build output followed by a final event.
+    pub fn queue(
+        &self,
+        image: Image,
+        source: ImageSource,
+        observer: Option<UnboundedSender<ImageBuildEvent>>,
+    ) {
+        let image_id = image
+            .id()
+            .expect("Images are saved before being built")
+            .to_owned();
+
+        let docker = self.docker.clone();
+        let repository = self.repository.clone();
+        let slots = self.slots.clone();
+
+        tokio::spawn(async move {
+            let _permit = slots
+                .acquire()
+                .await
+                .expect("The build slots are never closed");
+
+            let pending_image = image.clone();
+            let tag = image.tag().to_owned();
+            let (image, event) =
+                match DockerImage::create(docker, tag, source, observer.clone()).await {
+                    Ok(build_output) => {
+                        let image = image
+                            .built(
+                                build_output.log,
+                                build_output.docker_image_id,
+                                build_output.size,
+                            )
+                            .with_discovered_commands(build_output.commands.unwrap_or_default());
+                        let event = ImageBuildEvent::Completed {
+                            image: ImageDTO::from(image.clone()),
+                        };
+
+                        (image, event)
+                    }
+                    Err(error) => {
+                        warn!("Could not build image {}: {:?}", image_id, error);
+
+                        let message = error.to_string();
+                        let log = match error {
+                            DomainError::ImageBuildFailed { log, .. } => log,
+                            _ => vec![message.clone()],
+                        };
+
+                        (image.failed(log), ImageBuildEvent::Failed { message })
+                    }
+                };
+
+            // Commands discovered from the labels may hold values that cannot be saved
+            let (image, event) = match image.build_document() {
+                Ok(_) => (image, event),
+                Err(error) => {
+                    let message = format!("Invalid discovered commands: {}", error);
+
+                    (
+                        pending_image.failed(vec![message.clone()]),
+                        ImageBuildEvent::Failed { message },
+                    )
+                }
+            };
+
+            let saved = match image.build_document() {
+                Ok(document) => repository
+                    .update::<Image>(&image_id, document)
+                    .await
+                    .map(|_| ())
+                    .map_err(|error| error.to_string()),
+                Err(error) => Err(error.to_string()),
+            };
+
+            match saved {
+                Ok(()) => info!("Image {} is {:?}", image_id, image.status()),
+                Err(error) => warn!("Could not save build of image {}: {}", image_id, error),
+            }
+
+            if let Some(observer) = observer {
+                observer.send(event).ok();
+            }
+        });
+    }
+}
@@ -0,0 +1,97 @@
+use std::cmp::Ordering;
+
+use mongodb::bson::doc;
+use mongodb::bson::oid::ObjectId;
+use semver::Version;
+
+use crate::data::{Image, ImageStatus, ImageVersion, Repository};
+
+use super::DomainError;
+
+/// Versions are ordered following semver, so that the latest version of an image can be found.
+pub fn check_version(version: &str) -> Result<(), String> {
+    Version::parse(version)
+        .map(|_| ())
+        .map_err(|error| format!("Version {} is not a semver version: {}", version, error))
+}
+
+/// Orders versions following semver, the ones that are not valid semver versions coming first.
+fn compare_versions(left: &str, right: &str) -> Ordering {
+    match (Version::parse(left), Version::parse(right)) {
+        (Ok(left), Ok(right)) => left.cmp(&right),
+        (Ok(_), Err(_)) => Ordering::Greater,
+        (Err(_), Ok(_)) => Ordering::Less,
+        (Err(_), Err(_)) => left.cmp(right),
+    }
+}
+
+/// Returns every version of the image with this name, from the oldest to the latest.
+pub async fn versions(repository: &Repository, name: &str) -> Result<Vec<Image>, DomainError> {
+    let mut images = repository.find::<Image>(doc! { "tag.name": name }).await?;
+
+    images.sort_by(|left, right| compare_versions(&left.tag().version, &right.tag().version));
+
+    Ok(images)
+}
+
+/// Finds the image a reference resolves to: the image itself when it is pinned, or the latest
+/// version of the image with the same name that is ready to run.
+pub async fn resolve_image(
+    repository: &Repository,
+    image_id: &ObjectId,
+    image_version: ImageVersion,
+) -> Result<Option<Image>, DomainError> {
+    let Some(image) = repository.find_by_id::<Image>(image_id).await? else {
+        return Ok(None);
+    };
+
+    match image_version {
+        ImageVersion::Pinned => Ok(Some(image)),
+        ImageVersion::Latest => Ok(versions(repository, &image.tag().name)
+            .await?
+            .into_iter()
+            .rev()
+            .find(|version| version.status() == ImageStatus::Ready)
+            .or(Some(image))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_versions_following_semver() {
+        assert_eq!(compare_versions("1.2.0", "1.10.0"), Ordering::Less);
+        assert_eq!(compare_versions("2.0.0", "1.10.0"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0.0", "1.0.0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn pre_releases_come_before_the_release() {
+        assert_eq!(compare_versions("1.0.0-beta.1", "1.0.0"), Ordering::Less);
+        assert_eq!(
+            compare_versions("1.0.0-alpha", "1.0.0-beta"),
+            Ordering::Less
+        );
+        assert_eq!(compare_versions("1.0.0-beta.2", "0.9.0"), Ordering::Greater);
+    }
+
+    #[test]
+    fn non_semver_tags_come_first() {
+        assert_eq!(compare_versions("latest", "0.0.1"), Ordering::Less);
+        assert_eq!(compare_versions("0.0.1", "v2"), Ordering::Greater);
+    }
+
+    #[test]
+    fn non_semver_tags_are_ordered_as_text() {
+        assert_eq!(compare_versions("latest", "stable"), Ordering::Less);
+        assert_eq!(compare_versions("v2", "v10"), Ordering::Greater);
+    }
+
+    #[test]
+    fn checks_versions_are_semver() {
+        assert!(check_version("1.0.0-rc.1").is_ok());
+        assert!(check_version("latest").is_err());
+    }
+}
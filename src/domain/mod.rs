@@ -1,10 +1,32 @@
-pub use docker_image::DockerImage;
+pub use command_schemas::{check_schema, validate_arguments};
+pub use docker_image::{DockerImage, ImageSource};
 pub use docker_scenario_executor::DockerScenarioExecutor;
+pub use docker_suite_executor::DockerSuiteExecutor;
 pub use error::DomainError;
+pub use execution_analytics::{check_timezone, AnalyticsOptions, ExecutionAnalytics, Interval};
+pub use execution_queue::ExecutionQueue;
+pub use execution_report::ExecutionReport;
+pub use execution_registry::ExecutionRegistry;
+pub use execution_settings::ExecutionSettings;
+pub use image_build_queue::ImageBuildQueue;
+pub use image_versions::{check_version, resolve_image, versions};
+pub use step_assertions::check_expectations;
+pub use step_variables::check_captures;
 
+mod command_schemas;
 mod docker_image;
 mod docker_scenario_executor;
 mod docker_simulator;
+mod docker_suite_executor;
 mod error;
+mod execution_analytics;
+mod execution_queue;
+mod execution_report;
+mod execution_registry;
+mod execution_settings;
+mod image_build_queue;
+mod image_versions;
 mod running_docker_simulator;
+mod step_assertions;
+mod step_variables;
 
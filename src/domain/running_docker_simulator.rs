@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use bollard::container::RemoveContainerOptions;
 use bollard::Docker;
@@ -6,6 +7,19 @@ use warp::hyper;
 
 use super::DomainError;
 
+pub struct CommandResponse {
+    pub status: hyper::StatusCode,
+    pub body: String,
+}
+
+impl CommandResponse {
+    /// Parses the body as JSON, falling back to a JSON string for plain text responses.
+    pub fn json_body(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body)
+            .unwrap_or_else(|_| serde_json::Value::String(self.body.clone()))
+    }
+}
+
 #[derive(Clone)]
 pub struct RunningDockerSimulator {
     name: String,
@@ -30,7 +44,7 @@ impl RunningDockerSimulator {
 
     pub async fn is_ready(&self) -> bool {
         self.client
-            .get(&format!("http://localhost:{}/ready", self.port))
+            .get(format!("http://localhost:{}/ready", self.port))
             .send()
             .await
             .is_ok()
@@ -41,39 +55,35 @@ impl RunningDockerSimulator {
         step: usize,
         command: &str,
         arguments: &serde_json::Value,
-    ) -> Result<String, DomainError> {
+        timeout: Duration,
+    ) -> Result<CommandResponse, DomainError> {
         let url = format!("http://localhost:{}/command/{}", self.port, command);
 
-        let response = self.client.post(&url).json(&arguments).send().await;
-
-        match response {
-            Ok(response) => {
-                if response.status().is_success() {
-                    Ok(response.text().await.unwrap())
-                } else {
-                    let response_status = response.status();
-                    let response_text = response.text().await;
-
-                    match response_text {
-                        Ok(text) => Err(DomainError::SimulatorCommandFailed {
-                            step,
-                            message: text,
-                            status: response_status,
-                        }),
-                        Err(_) => Err(DomainError::SimulatorCommandFailed {
-                            step,
-                            message: String::from("Could not get error message"),
-                            status: response_status,
-                        }),
-                    }
+        let to_domain_error = |error: reqwest::Error| {
+            if error.is_timeout() {
+                DomainError::SimulatorCommandTimedOut { step, timeout }
+            } else {
+                DomainError::SimulatorCommandFailed {
+                    step,
+                    message: error.to_string(),
+                    status: hyper::StatusCode::INTERNAL_SERVER_ERROR,
                 }
             }
-            Err(error) => Err(DomainError::SimulatorCommandFailed {
-                step,
-                message: error.to_string(),
-                status: hyper::StatusCode::INTERNAL_SERVER_ERROR,
-            }),
-        }
+        };
+
+        let response = self
+            .client
+            .post(&url)
+            .json(&arguments)
+            .timeout(timeout)
+            .send()
+            .await
+            .map_err(to_domain_error)?;
+
+        let status = response.status();
+        let body = response.text().await.map_err(to_domain_error)?;
+
+        Ok(CommandResponse { status, body })
     }
 
     pub async fn remove(self) -> Result<(), DomainError> {
@@ -0,0 +1,441 @@
+use jsonschema::JSONSchema;
+use regex::Regex;
+use serde_json::{json, Value};
+use serde_json_path::JsonPath;
+
+use crate::data::{AssertionFailure, Expectation};
+
+use super::command_schemas::check_schema;
+use super::running_docker_simulator::CommandResponse;
+
+pub fn verify(expectations: &[Expectation], response: &CommandResponse) -> Vec<AssertionFailure> {
+    let body = response.json_body();
+
+    expectations
+        .iter()
+        .filter_map(|expectation| verify_expectation(expectation, response.status.as_u16(), &body))
+        .collect()
+}
+
+/// Compiles the paths, patterns and schemas of expectations, so that mistakes in them are caught
+/// when the scenario is saved rather than reported as failures of each run.
+pub fn check_expectations(expectations: &[Expectation]) -> Result<(), Vec<String>> {
+    let errors = expectations
+        .iter()
+        .enumerate()
+        .filter_map(|(i, expectation)| {
+            check_expectation(expectation)
+                .err()
+                .map(|error| format!("Expectation {}: {}", i + 1, error))
+        })
+        .collect::<Vec<_>>();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn check_expectation(expectation: &Expectation) -> Result<(), String> {
+    match expectation {
+        Expectation::Status { .. } => Ok(()),
+        Expectation::Equals { path, .. } | Expectation::InRange { path, .. } => check_path(path),
+        Expectation::Matches { path, pattern } => {
+            check_path(path)?;
+
+            Regex::new(pattern)
+                .map(|_| ())
+                .map_err(|error| format!("Invalid pattern {}: {}", pattern, error))
+        }
+        Expectation::Schema { schema } => check_schema(schema),
+    }
+}
+
+pub(super) fn check_path(path: &str) -> Result<(), String> {
+    JsonPath::parse(path)
+        .map(|_| ())
+        .map_err(|error| format!("Invalid path {}: {}", path, error))
+}
+
+fn verify_expectation(
+    expectation: &Expectation,
+    status: u16,
+    body: &Value,
+) -> Option<AssertionFailure> {
+    let failure = |expected: Value, actual: Value, message: String| {
+        Some(AssertionFailure {
+            expectation: expectation.clone(),
+            expected,
+            actual,
+            message,
+        })
+    };
+
+    match expectation {
+        Expectation::Status { status: expected } => {
+            if *expected == status {
+                return None;
+            }
+
+            failure(
+                json!(expected),
+                json!(status),
+                format!("Expected status {}, got {}", expected, status),
+            )
+        }
+        Expectation::Equals { path, value } => {
+            let actual = match query(path, body) {
+                Ok(actual) => actual,
+                Err(message) => return failure(value.clone(), Value::Null, message),
+            };
+
+            if actual == *value {
+                return None;
+            }
+
+            failure(
+                value.clone(),
+                actual,
+                format!("Value at {} is not the expected one", path),
+            )
+        }
+        Expectation::Matches { path, pattern } => {
+            let expected = json!(pattern);
+
+            let actual = match query(path, body) {
+                Ok(actual) => actual,
+                Err(message) => return failure(expected, Value::Null, message),
+            };
+
+            let regex = match Regex::new(pattern) {
+                Ok(regex) => regex,
+                Err(error) => {
+                    return failure(expected, actual, format!("Invalid pattern: {}", error))
+                }
+            };
+
+            let text = match &actual {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+
+            if regex.is_match(&text) {
+                return None;
+            }
+
+            failure(
+                expected,
+                actual,
+                format!("Value at {} does not match {}", path, pattern),
+            )
+        }
+        Expectation::InRange { path, min, max } => {
+            let expected = json!({ "min": min, "max": max });
+
+            let actual = match query(path, body) {
+                Ok(actual) => actual,
+                Err(message) => return failure(expected, Value::Null, message),
+            };
+
+            let number = match actual.as_f64() {
+                Some(number) => number,
+                None => {
+                    return failure(
+                        expected,
+                        actual,
+                        format!("Value at {} is not a number", path),
+                    )
+                }
+            };
+
+            let above_min = min.is_none_or(|min| number >= min);
+            let below_max = max.is_none_or(|max| number <= max);
+
+            if above_min && below_max {
+                return None;
+            }
+
+            failure(
+                expected,
+                actual,
+                format!("Value at {} is out of range", path),
+            )
+        }
+        Expectation::Schema { schema } => {
+            let compiled_schema = match JSONSchema::compile(schema) {
+                Ok(compiled_schema) => compiled_schema,
+                Err(error) => {
+                    return failure(
+                        schema.clone(),
+                        body.clone(),
+                        format!("Invalid schema: {}", error),
+                    )
+                }
+            };
+
+            let errors = match compiled_schema.validate(body) {
+                Ok(()) => return None,
+                Err(errors) => errors
+                    .map(|error| format!("{}: {}", error.instance_path, error))
+                    .collect::<Vec<_>>(),
+            };
+
+            failure(
+                schema.clone(),
+                body.clone(),
+                format!("Response does not match the schema: {}", errors.join(", ")),
+            )
+        }
+    }
+}
+
+/// Returns the single value found at the path, or an array when the path selects several of them.
+pub(super) fn query(path: &str, body: &Value) -> Result<Value, String> {
+    let json_path =
+        JsonPath::parse(path).map_err(|error| format!("Invalid path {}: {}", path, error))?;
+
+    let mut values = json_path.query(body).all();
+
+    match values.len() {
+        0 => Err(format!("No value found at {}", path)),
+        1 => Ok(values.remove(0).clone()),
+        _ => Ok(Value::Array(values.into_iter().cloned().collect())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use warp::hyper::StatusCode;
+
+    use super::*;
+
+    fn response(status: StatusCode, body: Value) -> CommandResponse {
+        CommandResponse {
+            status,
+            body: body.to_string(),
+        }
+    }
+
+    fn ok(body: Value) -> CommandResponse {
+        response(StatusCode::OK, body)
+    }
+
+    fn equals(path: &str, value: Value) -> Expectation {
+        Expectation::Equals {
+            path: String::from(path),
+            value,
+        }
+    }
+
+    fn in_range(path: &str, min: Option<f64>, max: Option<f64>) -> Expectation {
+        Expectation::InRange {
+            path: String::from(path),
+            min,
+            max,
+        }
+    }
+
+    #[test]
+    fn accepts_expectations_that_compile() {
+        let expectations = [
+            Expectation::Status { status: 200 },
+            equals("$.name", json!("meta")),
+            in_range("$.count", Some(1.0), None),
+            Expectation::Matches {
+                path: String::from("$.id"),
+                pattern: String::from("^[0-9a-f]+$"),
+            },
+            Expectation::Schema {
+                schema: json!({ "type": "object" }),
+            },
+        ];
+
+        assert_eq!(check_expectations(&expectations), Ok(()));
+    }
+
+    #[test]
+    fn reports_every_expectation_that_does_not_compile() {
+        let expectations = [
+            equals("name", json!("meta")),
+            Expectation::Matches {
+                path: String::from("$.id"),
+                pattern: String::from("[0-9"),
+            },
+            Expectation::Schema {
+                schema: json!({ "type": "unknown" }),
+            },
+        ];
+
+        let errors = check_expectations(&expectations).unwrap_err();
+
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].starts_with("Expectation 1: Invalid path name"));
+        assert!(errors[1].starts_with("Expectation 2: Invalid pattern [0-9"));
+        assert!(errors[2].starts_with("Expectation 3: Invalid schema"));
+    }
+
+    #[test]
+    fn passes_when_every_expectation_is_met() {
+        let failures = verify(
+            &[
+                Expectation::Status { status: 200 },
+                equals("$.message", json!("Hey, Rem113")),
+                Expectation::Matches {
+                    path: String::from("$.message"),
+                    pattern: String::from("^Hey"),
+                },
+                in_range("$.count", Some(1.0), Some(3.0)),
+            ],
+            &ok(json!({ "message": "Hey, Rem113", "count": 2 })),
+        );
+
+        assert!(failures.is_empty());
+    }
+
+    #[test]
+    fn reports_every_failing_expectation() {
+        let failures = verify(
+            &[
+                Expectation::Status { status: 201 },
+                equals("$.message", json!("Hello")),
+            ],
+            &ok(json!({ "message": "Hey" })),
+        );
+
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].expected, json!(201));
+        assert_eq!(failures[0].actual, json!(200));
+        assert_eq!(failures[1].expected, json!("Hello"));
+        assert_eq!(failures[1].actual, json!("Hey"));
+    }
+
+    #[test]
+    fn status_is_checked_on_error_responses() {
+        let expectations = [Expectation::Status { status: 404 }];
+
+        assert!(verify(&expectations, &response(StatusCode::NOT_FOUND, json!({}))).is_empty());
+        assert_eq!(
+            verify(&expectations, &response(StatusCode::OK, json!({}))).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn equals_compares_types_strictly() {
+        let failures = verify(&[equals("$.count", json!("2"))], &ok(json!({ "count": 2 })));
+
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].actual, json!(2));
+    }
+
+    #[test]
+    fn missing_paths_fail_with_a_null_actual_value() {
+        let failures = verify(&[equals("$.missing", json!(1))], &ok(json!({ "count": 1 })));
+
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].actual, Value::Null);
+        assert_eq!(failures[0].message, "No value found at $.missing");
+    }
+
+    #[test]
+    fn invalid_paths_fail() {
+        let failures = verify(&[equals("message", json!(1))], &ok(json!({ "message": 1 })));
+
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].message.starts_with("Invalid path message"));
+    }
+
+    #[test]
+    fn paths_selecting_several_values_give_an_array() {
+        let failures = verify(
+            &[equals("$.items[*].id", json!([1, 2]))],
+            &ok(json!({ "items": [{ "id": 1 }, { "id": 2 }] })),
+        );
+
+        assert!(failures.is_empty());
+    }
+
+    #[test]
+    fn matches_checks_non_string_values_as_json() {
+        let matches = |pattern: &str| Expectation::Matches {
+            path: String::from("$.count"),
+            pattern: String::from(pattern),
+        };
+        let body = ok(json!({ "count": 42 }));
+
+        assert!(verify(&[matches("^4\\d$")], &body).is_empty());
+        assert_eq!(verify(&[matches("^5")], &body).len(), 1);
+    }
+
+    #[test]
+    fn matches_fails_on_invalid_patterns() {
+        let failures = verify(
+            &[Expectation::Matches {
+                path: String::from("$.message"),
+                pattern: String::from("("),
+            }],
+            &ok(json!({ "message": "Hey" })),
+        );
+
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].message.starts_with("Invalid pattern"));
+    }
+
+    #[test]
+    fn in_range_bounds_are_inclusive_and_optional() {
+        let body = ok(json!({ "count": 5 }));
+
+        assert!(verify(&[in_range("$.count", Some(5.0), Some(5.0))], &body).is_empty());
+        assert!(verify(&[in_range("$.count", None, Some(10.0))], &body).is_empty());
+        assert!(verify(&[in_range("$.count", Some(0.0), None)], &body).is_empty());
+        assert_eq!(
+            verify(&[in_range("$.count", Some(6.0), None)], &body).len(),
+            1
+        );
+        assert_eq!(
+            verify(&[in_range("$.count", None, Some(4.5))], &body).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn in_range_fails_on_non_numbers() {
+        let failures = verify(
+            &[in_range("$.count", Some(0.0), None)],
+            &ok(json!({ "count": "5" })),
+        );
+
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].message, "Value at $.count is not a number");
+    }
+
+    #[test]
+    fn schema_reports_the_mismatching_fields() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "message": { "type": "string" } },
+            "required": ["message"],
+        });
+
+        let expectations = [Expectation::Schema { schema }];
+
+        assert!(verify(&expectations, &ok(json!({ "message": "Hey" }))).is_empty());
+
+        let failures = verify(&expectations, &ok(json!({ "message": 1 })));
+
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].message.contains("/message"));
+    }
+
+    #[test]
+    fn plain_text_bodies_are_json_strings() {
+        let response = CommandResponse {
+            status: StatusCode::OK,
+            body: String::from("pong"),
+        };
+
+        assert!(verify(&[equals("$", json!("pong"))], &response).is_empty());
+    }
+}
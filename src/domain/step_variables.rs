@@ -0,0 +1,285 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use regex::{Captures, Regex};
+use serde_json::Value;
+
+use crate::data::{Capture, Step};
+
+use super::step_assertions;
+
+/// Names of variables, which references can use.
+const NAME_PATTERN: &str = "[A-Za-z_][A-Za-z0-9_]*";
+
+/// Matches `{{ name }}` references to variables captured by previous steps.
+fn template_regex() -> &'static Regex {
+    static TEMPLATE_REGEX: OnceLock<Regex> = OnceLock::new();
+
+    TEMPLATE_REGEX.get_or_init(|| {
+        Regex::new(&format!(r"\{{\{{\s*({})\s*\}}\}}", NAME_PATTERN))
+            .expect("Invalid template regex")
+    })
+}
+
+fn name_regex() -> &'static Regex {
+    static NAME_REGEX: OnceLock<Regex> = OnceLock::new();
+
+    NAME_REGEX
+        .get_or_init(|| Regex::new(&format!("^{}$", NAME_PATTERN)).expect("Invalid name regex"))
+}
+
+/// Checks that every capture of the steps can be referenced, under a name no earlier capture
+/// took, and that its path compiles.
+pub fn check_captures(steps: &[Step]) -> Result<(), Vec<String>> {
+    let mut names = HashSet::new();
+    let mut errors = Vec::new();
+
+    for (i, step) in steps.iter().enumerate() {
+        for capture in &step.captures {
+            if !name_regex().is_match(&capture.name) {
+                errors.push(format!(
+                    "Step {}: capture name {:?} must match {}",
+                    i + 1,
+                    capture.name,
+                    NAME_PATTERN
+                ));
+            } else if !names.insert(capture.name.as_str()) {
+                errors.push(format!(
+                    "Step {}: variable {} is already captured by an earlier capture",
+                    i + 1,
+                    capture.name
+                ));
+            }
+
+            if let Err(error) = step_assertions::check_path(&capture.path) {
+                errors.push(format!("Step {}: {}", i + 1, error));
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+pub fn has_references(text: &str) -> bool {
+    template_regex().is_match(text)
+}
+
+/// Replaces variable references in every string of the arguments. A string made of a single
+/// reference is replaced by the captured value itself, so that numbers and objects keep their type.
+pub fn render(arguments: &Value, variables: &HashMap<String, Value>) -> Result<Value, String> {
+    match arguments {
+        Value::String(text) => render_string(text, variables),
+        Value::Array(values) => values
+            .iter()
+            .map(|value| render(value, variables))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        Value::Object(object) => object
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), render(value, variables)?)))
+            .collect::<Result<serde_json::Map<_, _>, String>>()
+            .map(Value::Object),
+        other => Ok(other.clone()),
+    }
+}
+
+fn render_string(text: &str, variables: &HashMap<String, Value>) -> Result<Value, String> {
+    let lookup = |name: &str| {
+        variables
+            .get(name)
+            .ok_or_else(|| format!("Variable {} is not defined", name))
+    };
+
+    if let Some(captures) = template_regex().captures(text) {
+        if captures[0].len() == text.len() {
+            return lookup(&captures[1]).cloned();
+        }
+    }
+
+    let mut error = None;
+
+    let rendered =
+        template_regex().replace_all(text, |captures: &Captures| match lookup(&captures[1]) {
+            Ok(Value::String(value)) => value.clone(),
+            Ok(value) => value.to_string(),
+            Err(message) => {
+                error.get_or_insert(message);
+                String::new()
+            }
+        });
+
+    match error {
+        Some(message) => Err(message),
+        None => Ok(Value::String(rendered.into_owned())),
+    }
+}
+
+pub fn capture(captures: &[Capture], body: &Value) -> Result<Vec<(String, Value)>, String> {
+    captures
+        .iter()
+        .map(|capture| {
+            step_assertions::query(&capture.path, body)
+                .map(|value| (capture.name.clone(), value))
+                .map_err(|message| format!("Could not capture {}: {}", capture.name, message))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::oid::ObjectId;
+    use serde_json::json;
+
+    use crate::data::{Command, ImageVersion};
+
+    use super::*;
+
+    fn variables() -> HashMap<String, Value> {
+        HashMap::from([
+            (String::from("name"), json!("Rem113")),
+            (String::from("count"), json!(3)),
+            (String::from("user"), json!({ "id": 7, "admin": false })),
+        ])
+    }
+
+    #[test]
+    fn substitutes_references_inside_strings() {
+        let rendered = render(&json!({ "greeting": "Hey, {{ name }}!" }), &variables());
+
+        assert_eq!(rendered, Ok(json!({ "greeting": "Hey, Rem113!" })));
+    }
+
+    #[test]
+    fn substitutes_several_references_with_or_without_spaces() {
+        let rendered = render(&json!("{{name}} has {{ count }} items"), &variables());
+
+        assert_eq!(rendered, Ok(json!("Rem113 has 3 items")));
+    }
+
+    #[test]
+    fn renders_nested_arrays_and_objects() {
+        let rendered = render(
+            &json!({ "names": ["{{ name }}", "Ninja"], "nested": { "count": "{{ count }}" } }),
+            &variables(),
+        );
+
+        assert_eq!(
+            rendered,
+            Ok(json!({ "names": ["Rem113", "Ninja"], "nested": { "count": 3 } }))
+        );
+    }
+
+    #[test]
+    fn single_references_keep_the_type_of_captures() {
+        assert_eq!(render(&json!("{{ count }}"), &variables()), Ok(json!(3)));
+        assert_eq!(
+            render(&json!("{{ user }}"), &variables()),
+            Ok(json!({ "id": 7, "admin": false }))
+        );
+    }
+
+    #[test]
+    fn non_string_captures_are_written_as_json_inside_strings() {
+        let rendered = render(&json!("user={{ user }}, count={{ count }}"), &variables());
+
+        assert_eq!(
+            rendered,
+            Ok(json!("user={\"id\":7,\"admin\":false}, count=3"))
+        );
+    }
+
+    #[test]
+    fn unknown_variables_fail() {
+        assert_eq!(
+            render(&json!({ "name": "{{ missing }}" }), &variables()),
+            Err(String::from("Variable missing is not defined"))
+        );
+        assert_eq!(
+            render(&json!("Hey, {{ missing }}"), &variables()),
+            Err(String::from("Variable missing is not defined"))
+        );
+    }
+
+    #[test]
+    fn values_without_references_are_kept() {
+        let arguments = json!({ "duration": 5000, "flag": true, "text": "{ not a reference }" });
+
+        assert_eq!(render(&arguments, &HashMap::new()), Ok(arguments.clone()));
+        assert!(!has_references("{ not a reference }"));
+        assert!(has_references("{{ name }}"));
+    }
+
+    #[test]
+    fn captures_values_at_paths() {
+        let captures = [Capture {
+            name: String::from("id"),
+            path: String::from("$.user.id"),
+        }];
+
+        assert_eq!(
+            capture(&captures, &json!({ "user": { "id": 7 } })),
+            Ok(vec![(String::from("id"), json!(7))])
+        );
+        assert_eq!(
+            capture(&captures, &json!({})),
+            Err(String::from(
+                "Could not capture id: No value found at $.user.id"
+            ))
+        );
+    }
+
+    fn step(captures: &[(&str, &str)]) -> Step {
+        Step {
+            image_id: ObjectId::new(),
+            image_version: ImageVersion::Pinned,
+            command: Command {
+                name: String::from("Hello"),
+                description: String::new(),
+                path: String::from("hello"),
+                timeout_ms: None,
+                arguments_schema: None,
+                response_schema: None,
+            },
+            arguments: json!({}),
+            timeout_ms: None,
+            expectations: Vec::new(),
+            captures: captures
+                .iter()
+                .map(|(name, path)| Capture {
+                    name: String::from(*name),
+                    path: String::from(*path),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn accepts_captures_that_can_be_referenced() {
+        let steps = [
+            step(&[("id", "$.id"), ("_token2", "$.token")]),
+            step(&[("name", "$.user.name")]),
+        ];
+
+        assert_eq!(check_captures(&steps), Ok(()));
+    }
+
+    #[test]
+    fn rejects_captures_that_cannot_be_referenced() {
+        let steps = [
+            step(&[("id", "$.id"), ("user-id", "$.user.id"), ("2fa", "$.code")]),
+            step(&[("id", "$.other.id"), ("name", "name")]),
+        ];
+
+        let errors = check_captures(&steps).unwrap_err();
+
+        assert_eq!(errors.len(), 4);
+        assert!(errors[0].starts_with("Step 1: capture name \"user-id\" must match"));
+        assert!(errors[1].starts_with("Step 1: capture name \"2fa\" must match"));
+        assert!(errors[2].starts_with("Step 2: variable id is already captured"));
+        assert!(errors[3].starts_with("Step 2: Invalid path name"));
+    }
+}
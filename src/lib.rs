@@ -0,0 +1,5 @@
+pub mod api;
+pub mod config;
+pub mod data;
+pub mod domain;
+pub mod loaders;
@@ -23,6 +23,7 @@ impl From<crate::domain::DomainError> for Error {
         match error {
             crate::domain::DomainError::Docker(error) => Error::Docker(error.to_string()),
             crate::domain::DomainError::Data(error) => Error::Database(error.to_string()),
+            crate::domain::DomainError::ImageBuildFailed { message, .. } => Error::Docker(message),
             other => panic!("Unexpected error while initializing the app: {:?}", other),
         }
     }
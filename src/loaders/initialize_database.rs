@@ -4,27 +4,26 @@ use mongodb::{Client, Database};
 use mongodb::bson::oid::ObjectId;
 use serde_json::json;
 
-use crate::data::{Command, Environment, Image, Scenario, Simulator, Step, Tag};
+use crate::config::DatabaseConfig;
+use crate::data::{
+    Command, Environment, Expectation, Image, ImageVersion, Scenario, Simulator, Step, Tag,
+};
 
 use super::Error;
 
-pub async fn initialize_database() -> Result<Database, Error> {
-    let client = Client::with_uri_str("mongodb://localhost:27017").await?;
-    let database = client.database("meta");
+pub async fn initialize_database(config: &DatabaseConfig) -> Result<Database, Error> {
+    let client = Client::with_uri_str(&config.uri).await?;
 
-    format_database(&database).await?;
-    populate_database(&database).await?;
-
-    Ok(database)
+    Ok(client.database(&config.name))
 }
 
-async fn format_database(database: &Database) -> Result<(), Error> {
+pub(super) async fn format_database(database: &Database) -> Result<(), Error> {
     database.drop(None).await?;
 
     Ok(())
 }
 
-async fn populate_database(database: &Database) -> Result<(), Error> {
+pub(super) async fn populate_database(database: &Database) -> Result<(), Error> {
     let environment_id = initialize_environments(database).await?;
     let (greeting_sim_id, manager_id) = initialize_images(database).await?;
     initialize_greeting_simulator(database, environment_id, greeting_sim_id).await?;
@@ -63,6 +62,17 @@ async fn initialize_images(database: &Database) -> Result<(ObjectId, ObjectId),
             name: String::from("Greet"),
             description: String::from("This command takes a name as a parameter, and returns a greeting for the specified name"),
             path: String::from("greet"),
+            timeout_ms: None,
+            arguments_schema: Some(json!({
+                "type": "object",
+                "properties": { "name": { "type": "string" } },
+                "required": ["name"],
+            })),
+            response_schema: Some(json!({
+                "type": "object",
+                "properties": { "message": { "type": "string" } },
+                "required": ["message"],
+            })),
         }],
     );
 
@@ -76,6 +86,13 @@ async fn initialize_images(database: &Database) -> Result<(ObjectId, ObjectId),
             name: String::from("Sleep"),
             description: String::from("This command takes a duration as a parameter, and sleeps for the specified duration"),
             path: String::from("sleep"),
+            timeout_ms: Some(10000),
+            arguments_schema: Some(json!({
+                "type": "object",
+                "properties": { "duration": { "type": "integer", "minimum": 0 } },
+                "required": ["duration"],
+            })),
+            response_schema: None,
         }],
     );
 
@@ -103,9 +120,9 @@ async fn initialize_greeting_simulator(
 
     let simulator = Simulator::new(
         String::from("greeting-sim"),
-        30000,
         environment_id,
         image_id,
+        ImageVersion::Pinned,
         HashMap::from([(String::from("GREETING"), String::from("Hey"))]),
     );
 
@@ -123,9 +140,9 @@ async fn initialize_manager(
 
     let simulator = Simulator::new(
         String::from("manager"),
-        30001,
         environment_id,
         image_id,
+        ImageVersion::Latest,
         HashMap::new(),
     );
 
@@ -149,43 +166,77 @@ async fn initialize_scenarios(
         vec![
             Step {
                 image_id: greeting_sim_image_id,
+                image_version: ImageVersion::Pinned,
                 command: Command {
                     name: String::from("Greet"),
                     description: String::from("Checks that the greeting is correct"),
                     path: String::from("greet"),
+                    timeout_ms: None,
+                    arguments_schema: None,
+                    response_schema: None,
                 },
                 arguments: json!({ "name": "Rem113" }),
+                timeout_ms: None,
+                expectations: vec![Expectation::Equals {
+                    path: String::from("$.message"),
+                    value: json!("Hey, Rem113"),
+                }],
+                captures: Vec::new(),
             },
             Step {
                 image_id: manager_image_id,
+                image_version: ImageVersion::Latest,
                 command: Command {
                     name: String::from("Sleep"),
                     description: String::from("Waits for 5 seconds"),
                     path: String::from("sleep"),
+                    timeout_ms: None,
+                    arguments_schema: None,
+                    response_schema: None,
                 },
                 arguments: json!({ "duration": 5000 }),
+                timeout_ms: None,
+                expectations: Vec::new(),
+                captures: Vec::new(),
             },
             Step {
                 image_id: greeting_sim_image_id,
+                image_version: ImageVersion::Pinned,
                 command: Command {
                     name: String::from("Greet"),
                     description: String::from(
-                        "This command is missing the name parameter, so it should fail",
+                        "This command expects the wrong greeting, so it should fail",
                     ),
                     path: String::from("greet"),
+                    timeout_ms: None,
+                    arguments_schema: None,
+                    response_schema: None,
                 },
-                arguments: json!({}),
+                arguments: json!({ "name": "Rem113" }),
+                timeout_ms: None,
+                expectations: vec![Expectation::Equals {
+                    path: String::from("$.message"),
+                    value: json!("Hello, Rem113"),
+                }],
+                captures: Vec::new(),
             },
             Step {
                 image_id: greeting_sim_image_id,
+                image_version: ImageVersion::Pinned,
                 command: Command {
                     name: String::from("Greet"),
                     description: String::from(
                         "Because the last step should fail, this command should never be run",
                     ),
                     path: String::from("greet"),
+                    timeout_ms: None,
+                    arguments_schema: None,
+                    response_schema: None,
                 },
                 arguments: json!({ "name": "Ninja" }),
+                timeout_ms: None,
+                expectations: Vec::new(),
+                captures: Vec::new(),
             },
         ],
     );
@@ -1,20 +1,35 @@
 use std::path::Path;
 use std::sync::Arc;
 
-use bollard::Docker;
+use bollard::{Docker, API_DEFAULT_VERSION};
 
-use crate::{data::Tag, domain::DockerImage};
+use crate::{
+    config::{DockerConfig, SeedConfig},
+    data::Tag,
+    domain::{DockerImage, ImageSource},
+};
 
 use super::Error;
 
-pub async fn initialize_docker() -> Result<Docker, Error> {
-    let docker = bollard::Docker::connect_with_local_defaults()?;
+/// Seconds before requests to the Docker daemon time out, the default of Bollard.
+const DOCKER_TIMEOUT: u64 = 120;
 
-    format_image_repository(docker.clone()).await?;
+pub async fn initialize_docker(config: &DockerConfig) -> Result<Docker, Error> {
+    let docker = match &config.host {
+        Some(host) if host.starts_with("unix://") => {
+            Docker::connect_with_unix(host, DOCKER_TIMEOUT, API_DEFAULT_VERSION)?
+        }
+        Some(host) => Docker::connect_with_http(host, DOCKER_TIMEOUT, API_DEFAULT_VERSION)?,
+        None => Docker::connect_with_local_defaults()?,
+    };
+
+    Ok(docker)
+}
 
+pub(super) async fn add_simulators(docker: Docker, seed_config: &SeedConfig) -> Result<(), Error> {
     add_simulator(
         docker.clone(),
-        "greeting-sim.tar.gz",
+        &seed_config.greeting_sim_archive,
         Tag {
             name: String::from("greeting-sim"),
             version: String::from("1.0.0"),
@@ -24,7 +39,7 @@ pub async fn initialize_docker() -> Result<Docker, Error> {
 
     add_simulator(
         docker.clone(),
-        "manager.tar.gz",
+        &seed_config.manager_archive,
         Tag {
             name: String::from("manager"),
             version: String::from("1.0.0"),
@@ -32,10 +47,10 @@ pub async fn initialize_docker() -> Result<Docker, Error> {
     )
         .await?;
 
-    Ok(docker)
+    Ok(())
 }
 
-async fn format_image_repository(docker: Docker) -> Result<(), Error> {
+pub(super) async fn format_image_repository(docker: Docker) -> Result<(), Error> {
     let docker = Arc::new(docker);
     let images = DockerImage::list(docker.clone()).await?;
 
@@ -68,7 +83,13 @@ async fn add_simulator(docker: Docker, path: impl AsRef<Path>, tag: Tag) -> Resu
         .await
         .map_err(|_| Error::Docker(String::from("Unexpected error while reading image file")))?;
 
-    DockerImage::create(Arc::new(docker), tag, image_file).await?;
+    DockerImage::create(
+        Arc::new(docker),
+        tag,
+        ImageSource::BuildContext(image_file),
+        None,
+    )
+    .await?;
 
     Ok(())
 }
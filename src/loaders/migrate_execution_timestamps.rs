@@ -0,0 +1,87 @@
+use futures::TryStreamExt;
+use mongodb::bson::doc;
+use mongodb::Database;
+use tracing::info;
+
+use crate::data::{to_bson_date, to_stored_bson, Execution, SuiteExecution};
+
+use super::Error;
+
+/// Dates used to be stored as RFC 3339 strings in the offset of the server, which do not sort in
+/// time order once the offset changes. They are stored as dates instead.
+pub async fn migrate_execution_timestamps(database: &Database) -> Result<(), Error> {
+    let executions = database.collection::<Execution>("Executions");
+    let string = doc! { "$type": "string" };
+
+    let legacy_executions = executions
+        .find(
+            doc! {
+                "$or": [
+                    { "timestamp": string.clone() },
+                    { "startedAt": string.clone() },
+                    { "endedAt": string.clone() },
+                    { "timings.setup.startedAt": string.clone() },
+                    { "timings.readiness.startedAt": string.clone() },
+                    { "timings.steps.startedAt": string.clone() },
+                    { "events.timing.startedAt": string.clone() },
+                ]
+            },
+            None,
+        )
+        .await?
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    // Reading the executions converted their dates already, so that they only need to be written
+    for execution in &legacy_executions {
+        executions
+            .update_one(
+                doc! { "_id": execution.id().expect("Saved executions have an ID") },
+                doc! {
+                    "$set": {
+                        "timestamp": to_bson_date(&execution.timestamp()),
+                        "startedAt": execution.started_at().as_ref().map(to_bson_date),
+                        "endedAt": execution.ended_at().as_ref().map(to_bson_date),
+                        "timings": to_stored_bson(execution.timings()).unwrap(),
+                        "events": to_stored_bson(execution.events()).unwrap(),
+                    }
+                },
+                None,
+            )
+            .await?;
+    }
+
+    if !legacy_executions.is_empty() {
+        info!(
+            "Stored the dates of {} executions as BSON dates",
+            legacy_executions.len()
+        );
+    }
+
+    let suite_executions = database.collection::<SuiteExecution>("SuiteExecutions");
+
+    let legacy_suite_executions = suite_executions
+        .find(doc! { "timestamp": string }, None)
+        .await?
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    for suite_execution in &legacy_suite_executions {
+        suite_executions
+            .update_one(
+                doc! { "_id": suite_execution.id().expect("Saved suite executions have an ID") },
+                doc! { "$set": { "timestamp": to_bson_date(&suite_execution.timestamp()) } },
+                None,
+            )
+            .await?;
+    }
+
+    if !legacy_suite_executions.is_empty() {
+        info!(
+            "Stored the timestamps of {} suite executions as BSON dates",
+            legacy_suite_executions.len()
+        );
+    }
+
+    Ok(())
+}
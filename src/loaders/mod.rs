@@ -1,8 +1,16 @@
 pub use error::Error;
 pub use initialize_database::initialize_database;
 pub use initialize_docker::initialize_docker;
+pub use migrate_execution_timestamps::migrate_execution_timestamps;
+pub use recover_executions::recover_executions;
+pub use recover_image_builds::recover_image_builds;
+pub use seed::{reset, seed};
 
 mod error;
 mod initialize_database;
 mod initialize_docker;
+mod migrate_execution_timestamps;
+mod recover_executions;
+mod recover_image_builds;
+mod seed;
 
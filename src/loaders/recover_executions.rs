@@ -0,0 +1,87 @@
+use chrono::Local;
+use futures::TryStreamExt;
+use mongodb::bson::{doc, to_bson};
+use mongodb::Database;
+use tracing::warn;
+
+use crate::data::{to_bson_date, Execution, ExecutionStatus, Runner, SuiteExecution};
+
+use super::Error;
+
+/// Executions that were queued or running when the server stopped will never end, so they are
+/// marked as errored. They end now, so that their duration is kept along with the others. The ones
+/// the command line is running are left alone.
+pub async fn recover_executions(database: &Database) -> Result<(), Error> {
+    let executions = database.collection::<Execution>("Executions");
+    let other_runner = to_bson(&Runner::Cli).unwrap();
+
+    let unfinished_statuses = ExecutionStatus::UNFINISHED
+        .iter()
+        .map(|status| to_bson(status).unwrap())
+        .collect::<Vec<_>>();
+
+    let interrupted_executions = executions
+        .find(
+            doc! {
+                "status": { "$in": unfinished_statuses },
+                "runner": { "$ne": other_runner.clone() },
+            },
+            None,
+        )
+        .await?
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let interrupted_count = interrupted_executions.len();
+    let ended_at = Local::now();
+
+    for execution in interrupted_executions {
+        let execution_id = *execution.id().expect("Saved executions have an ID");
+        let events = execution.events().clone();
+        let execution = execution.abort(events, ended_at);
+
+        // Only the outcome changes, the events being kept as they are
+        executions
+            .update_one(
+                doc! { "_id": execution_id },
+                doc! {
+                    "$set": {
+                        "status": to_bson(&execution.status()).unwrap(),
+                        "endedAt": execution.ended_at().as_ref().map(to_bson_date),
+                        "durationMs": execution.duration_ms().map(|duration_ms| duration_ms as i64),
+                    }
+                },
+                None,
+            )
+            .await?;
+    }
+
+    if interrupted_count > 0 {
+        warn!(
+            "Marked {} interrupted executions as errored",
+            interrupted_count
+        );
+    }
+
+    // Scenarios of suites left running were among the executions above
+    let result = database
+        .collection::<SuiteExecution>("SuiteExecutions")
+        .update_many(
+            doc! {
+                "status": to_bson(&ExecutionStatus::Running).unwrap(),
+                "runner": { "$ne": other_runner },
+            },
+            doc! { "$set": { "status": to_bson(&ExecutionStatus::Errored).unwrap() } },
+            None,
+        )
+        .await?;
+
+    if result.modified_count > 0 {
+        warn!(
+            "Marked {} interrupted suite executions as errored",
+            result.modified_count
+        );
+    }
+
+    Ok(())
+}
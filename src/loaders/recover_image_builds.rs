@@ -0,0 +1,35 @@
+use mongodb::bson::{doc, to_bson};
+use mongodb::Database;
+use tracing::warn;
+
+use crate::data::{Image, ImageStatus};
+
+use super::Error;
+
+/// Builds that were running when the server stopped will never end, so their images are marked as
+/// failed.
+pub async fn recover_image_builds(database: &Database) -> Result<(), Error> {
+    let images = database.collection::<Image>("Images");
+
+    let result = images
+        .update_many(
+            doc! { "status": to_bson(&ImageStatus::Building).unwrap() },
+            doc! {
+                "$set": {
+                    "status": to_bson(&ImageStatus::Failed).unwrap(),
+                    "buildLog": ["The server stopped before the build ended"],
+                }
+            },
+            None,
+        )
+        .await?;
+
+    if result.modified_count > 0 {
+        warn!(
+            "Marked {} interrupted image builds as failed",
+            result.modified_count
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use bollard::Docker;
+use mongodb::Database;
+use tracing::info;
+
+use crate::config::SeedConfig;
+
+use super::initialize_database::{format_database, populate_database};
+use super::initialize_docker::{add_simulators, format_image_repository};
+use super::Error;
+
+/// Adds the seed data only when the database is still empty, so that nothing is ever lost.
+pub async fn seed(database: &Database, docker: Docker, config: &SeedConfig) -> Result<(), Error> {
+    if !database.list_collection_names(None).await?.is_empty() {
+        info!("The database is not empty, the seed data is not added");
+        return Ok(());
+    }
+
+    add_simulators(docker, config).await?;
+    populate_database(database).await?;
+
+    info!("Added the seed data");
+
+    Ok(())
+}
+
+/// Removes every document and every Meta image from Docker, then adds the seed data again.
+pub async fn reset(database: &Database, docker: Docker, config: &SeedConfig) -> Result<(), Error> {
+    format_database(database).await?;
+    format_image_repository(docker.clone()).await?;
+
+    add_simulators(docker, config).await?;
+    populate_database(database).await?;
+
+    info!("Reset the database and the Docker images to the seed data");
+
+    Ok(())
+}
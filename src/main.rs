@@ -1,40 +1,62 @@
-use std::net::SocketAddr;
-use std::str::FromStr;
 use std::sync::Arc;
 
 use tracing_subscriber::{filter::EnvFilter, fmt};
 use warp::Filter;
 
-mod api;
-mod data;
-mod domain;
-mod loaders;
+use meta_backend::config::{Config, ServerCommand};
+use meta_backend::{api, loaders};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (config, command) = match Config::load() {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(2);
+        }
+    };
+
     let subscriber = fmt()
-        .with_env_filter(EnvFilter::new("trace,hyper=warn,tokio_util=warn,warp=info"))
+        .with_env_filter(EnvFilter::new(&config.server.log_filter))
         .finish();
     tracing::subscriber::set_global_default(subscriber)
         .expect("Could not set global default subscriber");
 
-    let docker = loaders::initialize_docker().await?;
+    let docker = loaders::initialize_docker(&config.docker).await?;
+    let database = loaders::initialize_database(&config.database).await?;
+
+    if let Some(ServerCommand::Reset) = command {
+        loaders::reset(&database, docker, &config.seed).await?;
+        return Ok(());
+    }
+
+    if config.seed.enabled {
+        loaders::seed(&database, docker.clone(), &config.seed).await?;
+    }
+
     let docker = Arc::new(docker);
 
-    let database = loaders::initialize_database().await?;
+    loaders::migrate_execution_timestamps(&database).await?;
+    loaders::recover_executions(&database).await?;
+    loaders::recover_image_builds(&database).await?;
     let database = Arc::new(database);
 
     let cors = warp::cors()
-        .allow_origin("http://localhost:1234")
+        .allow_origins(config.server.cors_origins.iter().map(String::as_str))
         .allow_methods(["GET", "POST", "PUT", "DELETE"])
         .allow_headers(["Content-Type"]);
     let api = warp::path("api")
-        .and(api::routes(database, docker))
+        .and(api::routes(
+            database,
+            docker,
+            config.execution.settings(),
+            config.execution.max_concurrent_builds,
+        ))
         .with(warp::trace::request())
         .recover(api::rejection_handler)
         .with(cors);
 
-    let address = SocketAddr::from_str("127.0.0.1:4000").expect("Could not parse address");
+    let address = config.server.address;
 
     let server = warp::serve(api).run(address);
 